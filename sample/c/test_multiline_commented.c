@@ -4,39 +4,39 @@
 
 int main() {
     // Test 1: Single line debug statement
-    // printf("debug: single line test\n");
+    //flop: printf("debug: single line test\n");
 
     // Test 2: Multiline with backslash continuation
-    // printf("debug: this is a very \
-long debug message that spans \
-multiple lines");
+    //flop: printf("debug: this is a very \
+//flop: long debug message that spans \
+//flop: multiple lines");
 
     // Test 3: Multiline fprintf with continuation
-    // fprintf(stderr, "DEBUG: error message \
-on multiple lines");
+    //flop: fprintf(stderr, "DEBUG: error message \
+//flop: on multiple lines");
 
     // Test 4: Normal output (should not be detected with --debug flag)
-    // printf("Normal single line\n");
+    //flop: printf("Normal single line\n");
 
     // Test 5: Multiline normal output
-    // printf("This is a normal \
-multiline message");
+    //flop: printf("This is a normal \
+//flop: multiline message");
 
     // Test 6: puts with debug
-    // puts("debug: testing puts");
+    //flop: puts("debug: testing puts");
 
     // Test 7: fputs multiline
-    // fputs("DEBUG: fputs \
-multiline test\n", stderr);
+    //flop: fputs("DEBUG: fputs \
+//flop: multiline test\n", stderr);
 
     // Test 8: Complex printf with multiple arguments
-    // printf("debug: value1=%d, value2=%d\n", 42, 100);
+    //flop: printf("debug: value1=%d, value2=%d\n", 42, 100);
 
     // Test 9: Very long multiline debug
-    // printf("debug: line1 \
-line2 \
-line3 \
-line4");
+    //flop: printf("debug: line1 \
+//flop: line2 \
+//flop: line3 \
+//flop: line4");
 
     return 0;
 }
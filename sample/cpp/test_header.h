@@ -0,0 +1,32 @@
+#ifndef TEST_HEADER_H
+#define TEST_HEADER_H
+
+#include <cstdio>
+#include <iostream>
+
+// Test file for output statements in headers, shared by C and C++ code
+
+// Test 1: C standard I/O in an inline function
+static inline void trace_value(int value) {
+    printf("debug: value=%d\n", value);
+}
+
+// Test 2: C++ stream in an inline function
+inline void trace_stream(int value) {
+    std::cerr << "debug" << value << std::endl;
+}
+
+// Test 3: Normal output (should not be detected with --debug flag)
+inline void report(int value) {
+    std::cout << "Result: " << value << std::endl;
+}
+
+template <typename T>
+void trace_all(const T& values) {
+    for (const auto& value : values) {
+        // Test 4: Stream in a template
+        std::clog << "DEBUG: " << value << std::endl;
+    }
+}
+
+#endif
@@ -0,0 +1,19 @@
+#include <cstdio>
+#include <iostream>
+
+// Test file for raw strings and digit separators
+
+int main() {
+    int total = 1'000'000;
+
+    // Test 1: Raw string containing quotes and semicolons
+    std::cerr << R"(debug: "quoted"; total)" << total << std::endl;
+
+    // Test 2: Raw string with a delimiter, spanning lines
+    std::cout << R"log(DEBUG: )" still inside;
+second line)log" << std::endl;
+
+    // Test 3: Character literals
+    printf("debug: %c%c\n", ';', '\'');
+    return 0;
+}
@@ -6,22 +6,22 @@ import (
 )
 
 func main() {
-	// fmt.Println("Starting application")
-	// fmt.Println("debug: initialization started")
+	//flop: fmt.Println("Starting application")
+	//flop: fmt.Println("debug: initialization started")
 
 	x := 42
-	// fmt.Printf("Value: %d\n", x)
+	//flop: fmt.Printf("Value: %d\n", x)
 
-	// log.Println("error: something went wrong")
-	// log.Println("DEBUG: detailed error info")
+	//flop: log.Println("error: something went wrong")
+	//flop: log.Println("DEBUG: detailed error info")
 
-	// fmt.Print("inline message")
+	//flop: fmt.Print("inline message")
 
 	result := calculate(10, 20)
-	// fmt.Println("Result:", result)
+	//flop: fmt.Println("Result:", result)
 }
 
 func calculate(a, b int) int {
-	// fmt.Printf("debug: calculating %d + %d\n", a, b)
+	//flop: fmt.Printf("debug: calculating %d + %d\n", a, b)
 	return a + b
 }
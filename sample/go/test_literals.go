@@ -0,0 +1,17 @@
+package main
+
+import "fmt"
+
+func main() {
+	name := "flop"
+
+	// Test 1: Raw string literals spanning lines
+	fmt.Println(`debug: raw ) string
+with "quotes" and \ backslashes`, name)
+
+	// Test 2: Rune literals
+	fmt.Printf("debug: %c %c\n", ')', '\'')
+
+	/* fmt.Println("in a comment") */
+	fmt.Println("Result:", len(name))
+}
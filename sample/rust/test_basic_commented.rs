@@ -1,22 +1,22 @@
 fn main() {
-    // println!("Starting application");
-    // println!("debug: initialization started");
+    //flop: println!("Starting application");
+    //flop: println!("debug: initialization started");
 
     let x = 42;
-    // println!("Value: {}", x);
+    //flop: println!("Value: {}", x);
 
-    // eprintln!("error: something went wrong");
-    // eprintln!("DEBUG: detailed error info");
+    //flop: eprintln!("error: something went wrong");
+    //flop: eprintln!("DEBUG: detailed error info");
 
-    // print!("inline message");
-    // eprint!("inline error");
+    //flop: print!("inline message");
+    //flop: eprint!("inline error");
 
     // Normal computation
     let result = calculate(10, 20);
-    // println!("Result: {}", result);
+    //flop: println!("Result: {}", result);
 }
 
 fn calculate(a: i32, b: i32) -> i32 {
-    // println!("debug: calculating {} + {}", a, b);
+    //flop: println!("debug: calculating {} + {}", a, b);
     a + b
 }
@@ -0,0 +1,21 @@
+fn main() {
+    let name = "flop";
+
+    // Test 1: Raw strings containing quotes and parentheses
+    println!(r#"debug: "{}" (raw)"#, name);
+    eprintln!(r"debug: path C:\tmp\{}", name);
+
+    // Test 2: Char literals and lifetimes
+    println!("debug: {} {}", ')', '"');
+    let first = first_word::<'static>(name);
+
+    // Test 3: Brace and bracket delimited macros
+    println!{"debug: braces {}", first}
+    dbg![first.len()];
+}
+
+fn first_word<'a>(text: &'a str) -> &'a str {
+    /* Nested /* block */ comment with println!("not code"); */
+    eprintln!("debug: splitting {:?}", text);
+    text.split(' ').next().unwrap_or_default()
+}
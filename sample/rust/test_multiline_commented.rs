@@ -0,0 +1,45 @@
+fn main() {
+    // Single line
+    //flop: println!("debug: single line");
+
+    // Multiline println
+    //flop: println!(
+        //flop: "debug: multiline message with value: {}",
+        //flop: compute_value()
+    //flop: );
+
+    // Complex expression
+    //flop: println!(
+        //flop: "Results: x={}, y={}, sum={}",
+        //flop: 10,
+        //flop: 20,
+        //flop: 10 + 20
+    //flop: );
+
+    // Nested function calls
+    //flop: eprintln!("debug: nested call result: {}", calculate(5, multiply(2, 3)));
+
+    // Very long multiline
+    //flop: println!(
+        //flop: "DEBUG: This is a very long debug message \
+         //flop: that spans multiple lines and contains \
+         //flop: important information: {}",
+        //flop: format_data()
+    //flop: );
+}
+
+fn compute_value() -> i32 {
+    dbg!(42)
+}
+
+fn calculate(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+fn multiply(a: i32, b: i32) -> i32 {
+    a * b
+}
+
+fn format_data() -> String {
+    String::from("formatted")
+}
@@ -0,0 +1,205 @@
+// Baseline of the output statements a project keeps on purpose, so that
+// `flop check` only fails on new ones. The baseline is meant to be committed.
+// Statements are identified by their file, enclosing function and text rather
+// than by line numbers, so that unrelated edits do not invalidate it.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::git;
+use crate::types::Match;
+
+pub const DEFAULT_FILE: &str = ".flop-baseline.json";
+
+// Baseline used when none is given: at the root of the repository containing
+// `path`, or in `path` itself outside of a repository
+pub fn default_file(path: &Path) -> PathBuf {
+    let dir = git::repository_root(path).unwrap_or_else(|| {
+        if path.is_dir() {
+            return path.to_path_buf();
+        }
+        match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        }
+    });
+    dir.join(DEFAULT_FILE)
+}
+
+#[derive(Serialize, Deserialize)]
+struct Baseline {
+    version: u32,
+    entries: Vec<Entry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    fingerprint: String,
+    // The rest is only there to make the baseline readable in reviews
+    file: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    function: Option<String>,
+    text: String,
+}
+
+// Record the statements in the baseline file, replacing its previous content
+pub fn write(file: &Path, matches: &[Match]) -> Result<()> {
+    let base_dir = base_dir(file);
+    let mut entries: Vec<Entry> = matches.iter().map(|m| entry(&base_dir, m)).collect();
+    entries.sort_by(|a, b| (&a.file, &a.function, &a.text).cmp(&(&b.file, &b.function, &b.text)));
+
+    let baseline = Baseline {
+        version: 1,
+        entries,
+    };
+    let json = serde_json::to_string_pretty(&baseline)? + "\n";
+    fs::write(file, json).with_context(|| format!("Failed to write file: {}", file.display()))
+}
+
+// The statements that are not in the baseline file. A statement recorded once
+// only excuses one of several identical statements in the same function.
+pub fn new_statements(file: &Path, matches: Vec<Match>) -> Result<Vec<Match>> {
+    let text = fs::read_to_string(file)
+        .with_context(|| format!("Failed to read baseline: {}", file.display()))?;
+    let baseline: Baseline = serde_json::from_str(&text)
+        .with_context(|| format!("Invalid baseline: {}", file.display()))?;
+
+    let mut known: HashMap<String, usize> = HashMap::new();
+    for entry in baseline.entries {
+        *known.entry(entry.fingerprint).or_default() += 1;
+    }
+
+    let base_dir = base_dir(file);
+    Ok(matches
+        .into_iter()
+        .filter(|m| match known.get_mut(&entry(&base_dir, m).fingerprint) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                false
+            }
+            _ => true,
+        })
+        .collect())
+}
+
+fn entry(base_dir: &Path, m: &Match) -> Entry {
+    // Paths are relative to the baseline, so that it does not depend on where
+    // flop runs from
+    let file = fs::canonicalize(&m.file_path)
+        .ok()
+        .and_then(|path| path.strip_prefix(base_dir).ok().map(PathBuf::from))
+        .unwrap_or_else(|| m.file_path.clone())
+        .to_string_lossy()
+        .replace('\\', "/");
+    // Reindenting or rewrapping a statement does not make it new
+    let text = m
+        .multiline_content
+        .iter()
+        .flat_map(|line| line.split_whitespace())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let function = m.function_name.clone();
+    let fingerprint = fingerprint(&[&file, function.as_deref().unwrap_or_default(), &text]);
+    Entry {
+        fingerprint,
+        file,
+        function,
+        text,
+    }
+}
+
+// FNV-1a, which unlike the standard library hashers is stable across Rust
+// versions and platforms
+fn fingerprint(parts: &[&str]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for part in parts {
+        for &byte in part.as_bytes().iter().chain(&[0]) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    format!("{:016x}", hash)
+}
+
+fn base_dir(file: &Path) -> PathBuf {
+    let dir = match file.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::ScanOptions;
+    use crate::finder::find_debug_printfs;
+
+    fn scan(path: &Path) -> Vec<Match> {
+        find_debug_printfs(path, false, true, &ScanOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn entry_is_relative_to_the_baseline_and_ignores_whitespace() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("src").join("m.c");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(
+            &path,
+            "int main() {\n    printf(\"a %d\",\n           1);\n}\n",
+        )
+        .unwrap();
+        let base_dir = base_dir(&dir.path().join(DEFAULT_FILE));
+
+        let entry = entry(&base_dir, &scan(&path)[0]);
+        assert_eq!(entry.file, "src/m.c");
+        assert_eq!(entry.function.as_deref(), Some("main"));
+        assert_eq!(entry.text, "printf(\"a %d\", 1);");
+
+        fs::write(&path, "\nint main() {\n  printf(\"a %d\", 1);\n}\n").unwrap();
+        assert_eq!(
+            entry.fingerprint,
+            super::entry(&base_dir, &scan(&path)[0]).fingerprint
+        );
+    }
+
+    #[test]
+    fn same_statement_in_another_function_is_new() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("m.c");
+        let file = dir.path().join(DEFAULT_FILE);
+        let main = "int main() {\n    printf(\"a\");\n    printf(\"a\");\n}\n";
+        fs::write(&path, main).unwrap();
+        write(&file, &scan(&path)).unwrap();
+
+        fs::write(
+            &path,
+            format!("void g() {{\n    printf(\"a\");\n}}\n{}", main),
+        )
+        .unwrap();
+        let new = new_statements(&file, scan(&path)).unwrap();
+        assert_eq!(new.len(), 1);
+        assert_eq!(new[0].function_name.as_deref(), Some("g"));
+        assert_eq!(new[0].line_number, 2);
+    }
+
+    #[test]
+    fn recorded_statement_only_excuses_one_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("m.c");
+        let file = dir.path().join(DEFAULT_FILE);
+        fs::write(&path, "int main() {\n    printf(\"a\");\n}\n").unwrap();
+        write(&file, &scan(&path)).unwrap();
+
+        fs::write(
+            &path,
+            "int main() {\n    printf(\"a\");\n    printf(\"a\");\n}\n",
+        )
+        .unwrap();
+        assert_eq!(new_statements(&file, scan(&path)).unwrap().len(), 1);
+    }
+}
@@ -1,4 +1,4 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -13,16 +13,61 @@ EXAMPLES:
     flop on -y                    Disable all output (batch, with confirmation)
     flop on -dy                   Disable debug output only (batch)
     flop off -p                   Preview what would be enabled
+    flop on --patch out.patch     Write the changes as a patch for review
     flop delete -d src/           Delete debug statements in src/ (interactive)
+    flop list --format json       List statements as JSON for scripts and editors
+    flop status                   Count active and disabled statements per file
+    flop check -d --changed       Fail if debug output was added since the last commit
+    flop baseline                 Accept the current statements; check then fails on new ones
+    flop undo                     Revert the last on/off/delete
+    flop undo --list              List the operations that can be reverted
 
 COMMON OPTIONS:
-    -d, --debug    Only process output statements containing 'debug' keyword
-    -y, --yes      Skip interactive selection (batch mode, confirmation still required)
-    -p, --preview  Preview mode - show what would be changed without modifying files
+    -d, --debug         Only process output statements containing 'debug' keyword
+    -y, --yes           Skip interactive selection (batch mode, confirmation still required)
+    -p, --preview       Preview mode - show the diff of the changes without modifying files
+    --patch <FILE>      Write the changes to a patch file instead of modifying files
+    -k, --keyword <RE>  Keyword regex for --debug instead of 'debug|DEBUG' (repeatable, implies --debug)
+    -i, --ignore-case   Match keywords case-insensitively
+    --include <GLOB>    Only process files matching the glob (repeatable)
+    --exclude <GLOB>    Skip files and directories matching the glob (repeatable)
+    --hidden            Also search hidden files and directories
+    --changed           Only process statements added since the last commit
+    --staged            Only process statements added in the index
+    --since <REF>       Only process statements added since a git ref
+    --any-comment       off/delete: also include statements commented out by hand
+    --color <WHEN>      Colors: auto (default, honors NO_COLOR), always or never
+    --encoding <ENC>    Encoding of files that are not valid UTF-8 ('auto' to detect)
+    --strict-encoding   Stop on files that cannot be decoded instead of skipping them
+    --skip-unreadable   Skip files that cannot be decoded, with a warning (the default)
+
+`flop on` marks the lines it disables with `//flop:`; `off` and `delete` only
+look at marked statements unless --any-comment is given.
+
+Files ignored by .gitignore, .ignore or .flopignore are skipped, as are
+.git/, target/, vendor/, node_modules/ and build/ directories. Binary files are
+skipped, and so are files that are not valid UTF-8, with a warning, unless
+--encoding is given. --strict-encoding stops the run on them instead.
 ")]
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
+    /// When to use colors ('auto' colors a terminal unless NO_COLOR is set)
+    #[arg(
+        long,
+        value_enum,
+        value_name = "WHEN",
+        default_value_t = ColorChoice::Auto,
+        global = true
+    )]
+    pub color: ColorChoice,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
 }
 
 #[derive(Subcommand)]
@@ -31,6 +76,9 @@ pub enum Commands {
     Off {
         /// Path to file or directory (defaults to current directory)
         path: Option<PathBuf>,
+        /// Also enable statements commented out by hand, not only those disabled by flop
+        #[arg(long)]
+        any_comment: bool,
         /// Only process output statements containing 'debug' keyword
         #[arg(short, long)]
         debug: bool,
@@ -40,6 +88,11 @@ pub enum Commands {
         /// Preview mode - show what would be changed without modifying files
         #[arg(short, long)]
         preview: bool,
+        /// Write the changes to a patch file for `git apply` instead of modifying files
+        #[arg(long, value_name = "FILE")]
+        patch: Option<PathBuf>,
+        #[command(flatten)]
+        scan: ScanOptions,
     },
     /// Comment out output statements (disable output)
     On {
@@ -54,11 +107,85 @@ pub enum Commands {
         /// Preview mode - show what would be changed without modifying files
         #[arg(short, long)]
         preview: bool,
+        /// Write the changes to a patch file for `git apply` instead of modifying files
+        #[arg(long, value_name = "FILE")]
+        patch: Option<PathBuf>,
+        #[command(flatten)]
+        scan: ScanOptions,
+    },
+    /// List output statements, both active and disabled by flop, without changing anything
+    List {
+        /// Path to file or directory (defaults to current directory)
+        path: Option<PathBuf>,
+        /// Also list statements commented out by hand, not only those disabled by flop
+        #[arg(long)]
+        any_comment: bool,
+        /// Only process output statements containing 'debug' keyword
+        #[arg(short, long)]
+        debug: bool,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+        #[command(flatten)]
+        scan: ScanOptions,
+    },
+    /// Summarize active and disabled output statements per file, language and function
+    Status {
+        /// Path to file or directory (defaults to current directory)
+        path: Option<PathBuf>,
+        /// Also count statements commented out by hand as disabled
+        #[arg(long)]
+        any_comment: bool,
+        /// Only process output statements containing 'debug' keyword
+        #[arg(short, long)]
+        debug: bool,
+        #[command(flatten)]
+        scan: ScanOptions,
+    },
+    /// Report active output statements as file:line:col and fail if there are any
+    Check {
+        /// Path to file or directory (defaults to current directory)
+        path: Option<PathBuf>,
+        /// Only process output statements containing 'debug' keyword
+        #[arg(short, long)]
+        debug: bool,
+        /// Output format ('text' is file:line:col: message)
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+        /// Only fail on statements missing from this baseline [default: .flop-baseline.json at the repository root, if present]
+        #[arg(long, value_name = "FILE")]
+        baseline: Option<PathBuf>,
+        #[command(flatten)]
+        scan: ScanOptions,
+    },
+    /// Record the active output statements in a baseline that `check` accepts
+    Baseline {
+        /// Path to file or directory (defaults to current directory)
+        path: Option<PathBuf>,
+        /// Only process output statements containing 'debug' keyword
+        #[arg(short, long)]
+        debug: bool,
+        /// Baseline file to write [default: .flop-baseline.json at the repository root, or in PATH]
+        #[arg(long, value_name = "FILE")]
+        output: Option<PathBuf>,
+        #[command(flatten)]
+        scan: ScanOptions,
+    },
+    /// Revert an operation recorded in the journal (the most recent one by default)
+    Undo {
+        /// Id of the operation to revert, as shown by --list
+        id: Option<u64>,
+        /// List the recorded operations, most recent first
+        #[arg(long, conflicts_with = "id")]
+        list: bool,
     },
     /// Delete output statements
     Delete {
         /// Path to file or directory (defaults to current directory)
         path: Option<PathBuf>,
+        /// Also delete statements commented out by hand, not only those disabled by flop
+        #[arg(long)]
+        any_comment: bool,
         /// Only process output statements containing 'debug' keyword
         #[arg(short, long)]
         debug: bool,
@@ -68,5 +195,97 @@ pub enum Commands {
         /// Preview mode - show what would be changed without modifying files
         #[arg(short, long)]
         preview: bool,
+        /// Write the changes to a patch file for `git apply` instead of modifying files
+        #[arg(long, value_name = "FILE")]
+        patch: Option<PathBuf>,
+        #[command(flatten)]
+        scan: ScanOptions,
     },
 }
+
+// How `list` and `check` print the statements
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Colored text, grouped by file
+    Text,
+    /// A JSON array of statements
+    Json,
+    /// One JSON object per line
+    Jsonl,
+    /// path:line:col:text lines, for quickfix lists, compilation-mode and fzf
+    Grep,
+    /// SARIF 2.1.0, for code scanning
+    Sarif,
+    /// Checkstyle XML, for CI dashboards
+    Checkstyle,
+}
+
+// Options shared by every subcommand that scans for statements
+#[derive(Args, Debug, Clone, Default)]
+pub struct ScanOptions {
+    #[command(flatten)]
+    pub keywords: KeywordOptions,
+    #[command(flatten)]
+    pub walk: WalkOptions,
+    #[command(flatten)]
+    pub diff: DiffOptions,
+    #[command(flatten)]
+    pub encoding: EncodingOptions,
+    // Set from --any-comment by the subcommands that look at commented statements
+    #[arg(skip)]
+    pub any_comment: bool,
+}
+
+// Which files are searched when walking a directory
+#[derive(Args, Debug, Clone, Default)]
+pub struct WalkOptions {
+    /// Only process files matching the glob (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    pub include: Vec<String>,
+    /// Skip files and directories matching the glob (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    pub exclude: Vec<String>,
+    /// Also search hidden files and directories
+    #[arg(long)]
+    pub hidden: bool,
+}
+
+// Limit matches to lines added according to git
+#[derive(Args, Debug, Clone, Default)]
+#[group(multiple = false)]
+pub struct DiffOptions {
+    /// Only process statements added since the last commit, including untracked files
+    #[arg(long)]
+    pub changed: bool,
+    /// Only process statements added in the index (staged changes)
+    #[arg(long)]
+    pub staged: bool,
+    /// Only process statements added since the given git ref
+    #[arg(long, value_name = "REF")]
+    pub since: Option<String>,
+}
+
+// How files that are not valid UTF-8 are handled
+#[derive(Args, Debug, Clone, Default)]
+pub struct EncodingOptions {
+    /// Encoding of files that are not valid UTF-8, like latin1 or shift_jis ('auto' to detect)
+    #[arg(long, value_name = "ENCODING")]
+    pub encoding: Option<String>,
+    /// Stop on files that cannot be decoded instead of skipping them with a warning
+    #[arg(long)]
+    pub strict_encoding: bool,
+    /// Skip files that cannot be decoded, with a warning (the default)
+    #[arg(long, conflicts_with = "strict_encoding")]
+    pub skip_unreadable: bool,
+}
+
+// Keywords that --debug looks for
+#[derive(Args, Debug, Clone, Default)]
+pub struct KeywordOptions {
+    /// Keyword regex for --debug instead of 'debug|DEBUG' (repeatable, implies --debug)
+    #[arg(short, long, value_name = "REGEX")]
+    pub keyword: Vec<String>,
+    /// Match keywords case-insensitively
+    #[arg(short = 'i', long)]
+    pub ignore_case: bool,
+}
@@ -0,0 +1,114 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::language;
+
+pub const CONFIG_FILE: &str = ".flop.toml";
+
+// Contents of a .flop.toml file. A config applies to its directory and everything
+// below it; a nested config overrides the settings of its parents for that subtree.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    // Same as passing --debug
+    pub debug: Option<bool>,
+    // Same as passing --yes
+    pub yes: Option<bool>,
+    // Same as passing --hidden
+    pub hidden: Option<bool>,
+    // Keyword regexes for --debug, used when no --keyword is given
+    pub keywords: Vec<String>,
+    // Same as passing --ignore-case
+    pub ignore_case: Option<bool>,
+    // Gitignore-style globs, relative to the directory of the config file
+    pub exclude: Vec<String>,
+    // Per-language additions, keyed by language name ("c", "cpp", "rust", "java", "go")
+    pub languages: BTreeMap<String, LanguageConfig>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LanguageConfig {
+    // Extra file extensions, without the leading dot
+    pub extensions: Vec<String>,
+    // Extra output functions; a trailing `!` makes it a Rust macro
+    pub functions: Vec<String>,
+    // Like `functions`, but always reported, even without the --debug keyword
+    pub debug_functions: Vec<String>,
+}
+
+impl Config {
+    // Load the config file in `dir`, if there is one
+    pub fn load(dir: &Path) -> Result<Option<Self>> {
+        let path = dir.join(CONFIG_FILE);
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config: {}", path.display()))?;
+        let config: Config = toml::from_str(&content)
+            .with_context(|| format!("Invalid config: {}", path.display()))?;
+
+        for name in config.languages.keys() {
+            if !language::all().iter().any(|lang| lang.name() == name) {
+                bail!("Unknown language '{}' in config: {}", name, path.display());
+            }
+        }
+
+        Ok(Some(config))
+    }
+
+    // Merge every config from the file system root down to `path`
+    pub fn for_path(path: &Path) -> Result<Self> {
+        let mut config = Config::default();
+        for dir in config_dirs(path)?.iter().rev() {
+            if let Some(child) = Config::load(dir)? {
+                config = config.merge(&child);
+            }
+        }
+        Ok(config)
+    }
+
+    // Settings of `child` win, lists included: a list set in `child` replaces the
+    // list of its parent
+    pub fn merge(&self, child: &Config) -> Config {
+        let mut languages = self.languages.clone();
+        for (name, lang) in &child.languages {
+            let merged = languages.entry(name.clone()).or_default();
+            merged.extensions = override_list(&merged.extensions, &lang.extensions);
+            merged.functions = override_list(&merged.functions, &lang.functions);
+            merged.debug_functions = override_list(&merged.debug_functions, &lang.debug_functions);
+        }
+
+        Config {
+            debug: child.debug.or(self.debug),
+            yes: child.yes.or(self.yes),
+            hidden: child.hidden.or(self.hidden),
+            keywords: override_list(&self.keywords, &child.keywords),
+            ignore_case: child.ignore_case.or(self.ignore_case),
+            exclude: child.exclude.clone(),
+            languages,
+        }
+    }
+}
+
+// An empty list is not set
+fn override_list(parent: &[String], child: &[String]) -> Vec<String> {
+    if child.is_empty() { parent } else { child }.to_vec()
+}
+
+// `path` (or its directory, for files) and all of its ancestors, innermost first
+fn config_dirs(path: &Path) -> Result<Vec<PathBuf>> {
+    let path = fs::canonicalize(path)
+        .with_context(|| format!("Failed to resolve path: {}", path.display()))?;
+    let dir = if path.is_file() {
+        path.parent().map(Path::to_path_buf).unwrap_or_default()
+    } else {
+        path
+    };
+    Ok(dir.ancestors().map(Path::to_path_buf).collect())
+}
@@ -0,0 +1,81 @@
+// Unified diffs of the edits flop would make: shown by --preview, and written by
+// --patch so that the changes can go through review and `git apply`.
+
+use anyhow::{Context, Result};
+use similar::TextDiff;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::editor::FileEdit;
+use crate::git;
+use crate::ui::paint;
+
+const CONTEXT_LINES: usize = 3;
+
+pub fn print_diff(file_edits: &[FileEdit]) {
+    for file_edit in file_edits {
+        let name = patch_path(&file_edit.path, None);
+        let diff = unified_diff(&file_edit.source.text, &file_edit.edited, &name);
+        for line in diff.lines() {
+            let line = line.trim_end_matches('\r');
+            let style = match line.as_bytes().first() {
+                _ if ["diff ", "---", "+++"].iter().any(|p| line.starts_with(p)) => Some("1"),
+                Some(b'@') => Some("36"),
+                Some(b'-') => Some("31"),
+                Some(b'+') => Some("32"),
+                _ => None,
+            };
+            match style {
+                Some(style) => println!("{}", paint(style, line)),
+                None => println!("{}", line),
+            }
+        }
+    }
+}
+
+// Write the edits as a patch, each file in its own encoding and keeping its
+// byte order mark, so that `git apply` reproduces them exactly
+pub fn write_patch(file_edits: &[FileEdit], out: &Path) -> Result<()> {
+    let root = file_edits
+        .first()
+        .and_then(|file_edit| git::repository_root(&file_edit.path));
+
+    let mut patch = Vec::new();
+    for file_edit in file_edits {
+        let name = patch_path(&file_edit.path, root.as_deref());
+        let source = &file_edit.source;
+        let diff = unified_diff(
+            &source.with_bom(&source.text),
+            &source.with_bom(&file_edit.edited),
+            &name,
+        );
+        let (bytes, _, _) = source.encoding().encode(&diff);
+        patch.extend_from_slice(&bytes);
+    }
+
+    fs::write(out, patch).with_context(|| format!("Failed to write file: {}", out.display()))
+}
+
+fn unified_diff(old: &str, new: &str, name: &str) -> String {
+    if old == new {
+        return String::new();
+    }
+    let header = format!("diff --git a/{name} b/{name}\n");
+    let diff = TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(CONTEXT_LINES)
+        .header(&format!("a/{}", name), &format!("b/{}", name))
+        .to_string();
+    header + &diff
+}
+
+// Path of a file in the patch: relative to the repository root, where
+// `git apply` resolves paths, or as given outside of a repository
+fn patch_path(path: &Path, root: Option<&Path>) -> String {
+    let relative = root.and_then(|root| {
+        let absolute = fs::canonicalize(path).ok()?;
+        absolute.strip_prefix(root).ok().map(PathBuf::from)
+    });
+    let path = relative.unwrap_or_else(|| path.strip_prefix(".").unwrap_or(path).to_path_buf());
+    path.to_string_lossy().replace('\\', "/")
+}
@@ -1,103 +1,509 @@
 use anyhow::{Context, Result};
-use regex::Regex;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
+use crate::journal::{self, EditRange, FileChange};
+use crate::language::{self, DISABLED_TAG};
+use crate::line_index::LineIndex;
+use crate::source::{self, SourceFile};
 use crate::types::Match;
 
-pub fn apply_changes(matches: &[Match], uncomment: bool) -> Result<()> {
-    // Group matches by file
-    let mut files_map: HashMap<PathBuf, Vec<&Match>> = HashMap::new();
+// What is left of a disabled or deleted statement that was the body of an `if`,
+// loop or label, so that it does not take the next statement as its body
+const EMPTY_BODY: &str = "{}";
 
-    for m in matches {
-        files_map.entry(m.file_path.clone()).or_default().push(m);
+// A replacement of the byte range `start..end`
+struct Edit {
+    start: usize,
+    end: usize,
+    text: String,
+}
+
+// The new content of a file, before it is written
+pub struct FileEdit {
+    pub path: PathBuf,
+    pub source: SourceFile,
+    pub edited: String,
+    // Statements edited, leaving out those that had to be skipped
+    pub statements: usize,
+    ranges: Vec<EditRange>,
+}
+
+// Statements edited across files
+pub fn statement_count(file_edits: &[FileEdit]) -> usize {
+    file_edits
+        .iter()
+        .map(|file_edit| file_edit.statements)
+        .sum()
+}
+
+// Returns the number of statements edited, and the changes made to each file
+// for the journal
+pub fn apply_changes(matches: &[Match], uncomment: bool) -> Result<(usize, Vec<FileChange>)> {
+    write_edits(plan_changes(matches, uncomment)?)
+}
+
+pub fn delete_changes(matches: &[Match]) -> Result<(usize, Vec<FileChange>)> {
+    write_edits(plan_delete(matches)?)
+}
+
+// Write every edited file, or none of them if one fails
+pub fn write_edits(file_edits: Vec<FileEdit>) -> Result<(usize, Vec<FileChange>)> {
+    let statements = statement_count(&file_edits);
+    let mut writes = Vec::new();
+    let mut changes = Vec::new();
+    for file_edit in file_edits {
+        // All of its statements may have been skipped
+        if file_edit.edited == file_edit.source.text {
+            continue;
+        }
+        let path = fs::canonicalize(&file_edit.path)
+            .with_context(|| format!("Failed to read file: {}", file_edit.path.display()))?;
+        changes.push(FileChange {
+            path,
+            encoding: file_edit.source.encoding().name().to_string(),
+            hunks: journal::hunks(&file_edit.source.text, &file_edit.edited, &file_edit.ranges),
+        });
+        let bytes = file_edit
+            .source
+            .encode(&file_edit.path, &file_edit.edited)?;
+        writes.push((file_edit.path, bytes));
     }
 
-    for (file_path, file_matches) in files_map {
-        let content = fs::read_to_string(&file_path)?;
-        let mut lines: Vec<String> = content.lines().map(|s| s.to_string()).collect();
-
-        // Sort by line number in reverse order to avoid index shifting
-        let mut sorted_matches = file_matches;
-        sorted_matches.sort_by(|a, b| b.line_number.cmp(&a.line_number));
-
-        for m in sorted_matches {
-            // For multiline statements, only comment/uncomment the first line
-            // This will effectively disable/enable the entire statement
-            let idx = m.line_number - 1;
-            if idx < lines.len() {
-                if uncomment {
-                    // Remove the comment
-                    lines[idx] = uncomment_line(&lines[idx]);
+    source::write_all(writes)?;
+    Ok((statements, changes))
+}
+
+// Edits that enable (`uncomment`) or disable the statements, without writing them
+pub fn plan_changes(matches: &[Match], uncomment: bool) -> Result<Vec<FileEdit>> {
+    let mut file_edits = Vec::new();
+    for (file_path, file_matches) in group_by_file(matches) {
+        let source = SourceFile::read_as(&file_path, file_matches[0].encoding)?;
+        let content = &source.text;
+        let Some(file_matches) = relocate(&file_path, content, file_matches) else {
+            continue;
+        };
+        let lines = LineIndex::new(content);
+        let lang = language::for_path(&file_path);
+        let marker = lang.map_or("//", |lang| lang.line_comment());
+        let (open, close) = lang.map_or(("/*", "*/"), |lang| lang.block_comment());
+
+        let mut edits = Vec::new();
+        let mut statements = file_matches.len();
+        // A line may belong to more than one statement, so only touch it once
+        let mut lines_to_change: HashSet<usize> = HashSet::new();
+
+        if uncomment {
+            for m in &file_matches {
+                if let Some((open_start, close_end)) = block_comment_around(content, m, open, close)
+                {
+                    edits.push(Edit {
+                        start: open_start,
+                        end: m.start_byte,
+                        text: String::new(),
+                    });
+                    let close_end =
+                        if m.control_body && content[close_end..].starts_with(EMPTY_BODY) {
+                            close_end + EMPTY_BODY.len()
+                        } else {
+                            close_end
+                        };
+                    edits.push(Edit {
+                        start: m.end_byte,
+                        end: close_end,
+                        text: String::new(),
+                    });
                 } else {
-                    // Add comment
-                    lines[idx] = comment_line(&lines[idx]);
+                    lines_to_change.extend(m.line_number..=m.end_line_number);
                 }
             }
+        } else {
+            let spans: Vec<_> = file_matches
+                .iter()
+                .map(|m| (m.start_byte, m.end_byte))
+                .collect();
+            let whole = whole_lines(content, &lines, &spans);
+
+            for (m, whole) in file_matches.iter().zip(whole) {
+                // Comment every line of the statement so that multiline statements are
+                // disabled as a whole. The body of an `if` or loop needs a statement
+                // in its place instead.
+                if whole && !m.control_body {
+                    lines_to_change.extend(m.line_number..=m.end_line_number);
+                    continue;
+                }
+
+                // The statement shares a line with other code: comment out just its span
+                if content[m.start_byte..m.end_byte].contains(close) {
+                    eprintln!(
+                        "Warning: cannot comment out {}:{}, the statement contains '{}'",
+                        file_path.display(),
+                        m.line_number,
+                        close
+                    );
+                    statements -= 1;
+                    continue;
+                }
+                edits.push(Edit {
+                    start: m.start_byte,
+                    end: m.start_byte,
+                    text: format!("{}{} ", open, DISABLED_TAG),
+                });
+                edits.push(Edit {
+                    start: m.end_byte,
+                    end: m.end_byte,
+                    text: format!(" {}{}", close, if m.control_body { EMPTY_BODY } else { "" }),
+                });
+            }
         }
 
-        let new_content = lines.join("\n") + "\n";
-        fs::write(&file_path, new_content)
-            .with_context(|| format!("Failed to write file: {}", file_path.display()))?;
+        for line_num in lines_to_change {
+            let range = lines.line_range(line_num);
+            let line = &content[range.clone()];
+            let text = if uncomment {
+                uncomment_line(line, marker)
+            } else {
+                comment_line(line, marker)
+            };
+            edits.push(Edit {
+                start: range.start,
+                end: range.end,
+                text,
+            });
+        }
+
+        let (edited, ranges) = apply_edits(content, edits);
+        file_edits.push(FileEdit {
+            path: file_path,
+            source,
+            edited,
+            statements,
+            ranges,
+        });
     }
 
-    Ok(())
+    Ok(file_edits)
 }
 
-pub fn delete_changes(matches: &[Match]) -> Result<()> {
-    // Group matches by file
-    let mut files_map: HashMap<PathBuf, Vec<&Match>> = HashMap::new();
+// Edits that delete the statements, without writing them
+pub fn plan_delete(matches: &[Match]) -> Result<Vec<FileEdit>> {
+    let mut file_edits = Vec::new();
+    for (file_path, file_matches) in group_by_file(matches) {
+        let source = SourceFile::read_as(&file_path, file_matches[0].encoding)?;
+        let content = &source.text;
+        let Some(file_matches) = relocate(&file_path, content, file_matches) else {
+            continue;
+        };
+        let lines = LineIndex::new(content);
+        let lang = language::for_path(&file_path);
+        let marker = lang.map_or("//", |lang| lang.line_comment());
+        let (open, close) = lang.map_or(("/*", "*/"), |lang| lang.block_comment());
+
+        // Commented statements are deleted along with their comment. A `//` comment
+        // may hold other statements, and only goes when its lines are deleted.
+        let spans: Vec<_> = file_matches
+            .iter()
+            .map(|m| {
+                block_comment_around(content, m, open, close)
+                    .or_else(|| line_comment_around(content, &lines, m, marker))
+                    .unwrap_or((m.start_byte, m.end_byte))
+            })
+            .collect();
+        let whole = whole_lines(content, &lines, &spans);
+
+        let mut edits = Vec::new();
+        // Collect line numbers to delete (all lines from start to end of each statement)
+        let mut lines_to_delete: HashSet<usize> = HashSet::new();
+        for ((m, &(start, end)), whole) in file_matches.iter().zip(&spans).zip(whole) {
+            if m.control_body {
+                // A disabled body already has its empty block
+                let text = if content[end..].starts_with(EMPTY_BODY) {
+                    ""
+                } else {
+                    EMPTY_BODY
+                };
+                edits.push(Edit {
+                    start,
+                    end,
+                    text: text.to_string(),
+                });
+                continue;
+            }
+            if whole {
+                lines_to_delete.extend(lines.line_number(start)..=lines.line_number(end));
+                continue;
+            }
+            let (start, end) =
+                if m.commented && block_comment_around(content, m, open, close).is_none() {
+                    (m.start_byte, m.end_byte)
+                } else {
+                    (start, end)
+                };
+
+            // Remove just the statement, along with the blanks separating it from
+            // the code that follows (or precedes, at the end of the line)
+            let line_end = lines.line_range(lines.line_number(end)).end;
+            let trailing = content[end..line_end].len()
+                - content[end..line_end].trim_start_matches([' ', '\t']).len();
+            let (start, end) = if end + trailing == line_end {
+                let line_start = lines.line_start(start);
+                let leading = content[line_start..start].len()
+                    - content[line_start..start]
+                        .trim_end_matches([' ', '\t'])
+                        .len();
+                (start - leading, end)
+            } else {
+                (start, end + trailing)
+            };
+            edits.push(Edit {
+                start,
+                end,
+                text: String::new(),
+            });
+        }
+
+        // Delete runs of whole lines along with their line terminators
+        let mut lines_to_delete: Vec<usize> = lines_to_delete.into_iter().collect();
+        lines_to_delete.sort_unstable();
+        for run in lines_to_delete.chunk_by(|a, b| a + 1 == *b) {
+            let (first, last) = (run[0], run[run.len() - 1]);
+            let mut start = lines.line_range(first).start;
+            let end = if last < lines.line_count() {
+                lines.line_range(last + 1).start
+            } else {
+                content.len()
+            };
+            // The last line has no terminator: remove the one before it instead,
+            // so the file still ends without a newline
+            if end == content.len() && !content.ends_with('\n') && first > 1 {
+                start = lines.line_range(first - 1).end;
+            }
+            edits.push(Edit {
+                start,
+                end,
+                text: String::new(),
+            });
+        }
+
+        let (edited, ranges) = apply_edits(content, edits);
+        file_edits.push(FileEdit {
+            path: file_path,
+            source,
+            edited,
+            statements: file_matches.len(),
+            ranges,
+        });
+    }
 
+    Ok(file_edits)
+}
+
+// Matches of each file, in path order
+fn group_by_file(matches: &[Match]) -> BTreeMap<PathBuf, Vec<&Match>> {
+    let mut files_map: BTreeMap<PathBuf, Vec<&Match>> = BTreeMap::new();
     for m in matches {
         files_map.entry(m.file_path.clone()).or_default().push(m);
     }
+    files_map
+}
+
+// The statements of a file as they are in its current content. A file edited
+// since it was scanned (say, autosaved while the selection UI was open) has
+// each statement found again by its text; if one cannot be found unambiguously
+// the file is left alone.
+fn relocate(file_path: &Path, content: &str, file_matches: Vec<&Match>) -> Option<Vec<Match>> {
+    let hash = source::content_hash(content);
+    if file_matches.iter().all(|m| m.file_hash == hash) {
+        return Some(file_matches.into_iter().cloned().collect());
+    }
 
-    for (file_path, file_matches) in files_map {
-        let content = fs::read_to_string(&file_path)?;
-        let lines: Vec<String> = content.lines().map(|s| s.to_string()).collect();
+    let lines = LineIndex::new(content);
+    let terminator = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    file_matches
+        .into_iter()
+        .map(|m| {
+            let text = m.multiline_content.join(terminator);
+            let start = if content.get(m.start_byte..m.end_byte) == Some(text.as_str()) {
+                m.start_byte
+            } else {
+                let mut found = content.match_indices(&text).map(|(i, _)| i);
+                match (found.next(), found.next()) {
+                    (Some(start), None) => start,
+                    _ => {
+                        eprintln!(
+                            "Warning: {} changed since it was scanned and the statement at line {} cannot be found again; leaving the file unchanged",
+                            file_path.display(),
+                            m.line_number
+                        );
+                        return None;
+                    }
+                }
+            };
+            let end = start + text.len();
+            Some(Match {
+                line_number: lines.line_number(start),
+                end_line_number: lines.line_number(end),
+                start_byte: start,
+                end_byte: end,
+                start_column: start - lines.line_start(start) + 1,
+                end_column: end - lines.line_start(end) + 1,
+                file_hash: hash,
+                ..m.clone()
+            })
+        })
+        .collect()
+}
 
-        // Sort by line number in reverse order to avoid index shifting
-        let mut sorted_matches = file_matches;
-        sorted_matches.sort_by(|a, b| b.line_number.cmp(&a.line_number));
+// For each span, whether every line it touches holds nothing but selected spans,
+// so that it can be edited line by line
+fn whole_lines(content: &str, lines: &LineIndex, spans: &[(usize, usize)]) -> Vec<bool> {
+    let line_ranges: Vec<_> = spans
+        .iter()
+        .map(|&(start, end)| lines.line_number(start)..=lines.line_number(end))
+        .collect();
 
-        // Collect line numbers to delete (all lines from start to end of each statement)
-        let mut lines_to_delete: HashSet<usize> = HashSet::new();
-        for m in sorted_matches {
-            for line_num in m.line_number..=m.end_line_number {
-                lines_to_delete.insert(line_num - 1);
+    let mut sorted = spans.to_vec();
+    sorted.sort();
+    let mut candidates: HashSet<usize> = line_ranges
+        .iter()
+        .flat_map(|r| r.clone())
+        .filter(|&line_num| {
+            let range = lines.line_range(line_num);
+            let mut pos = range.start;
+            for &(start, end) in &sorted {
+                if end <= range.start || start >= range.end {
+                    continue;
+                }
+                if start > pos && !content[pos..start].trim().is_empty() {
+                    return false;
+                }
+                pos = pos.max(end);
             }
+            pos >= range.end || content[pos..range.end].trim().is_empty()
+        })
+        .collect();
+
+    // A span edited by its exact range keeps all of its lines from being edited
+    // as a whole, which may in turn affect spans sharing those lines
+    loop {
+        let mut changed = false;
+        for r in &line_ranges {
+            if !r.clone().all(|l| candidates.contains(&l)) {
+                for l in r.clone() {
+                    changed |= candidates.remove(&l);
+                }
+            }
+        }
+        if !changed {
+            break;
         }
+    }
 
-        // Filter out lines to delete
-        let new_lines: Vec<String> = lines
-            .into_iter()
-            .enumerate()
-            .filter(|(idx, _)| !lines_to_delete.contains(idx))
-            .map(|(_, line)| line)
-            .collect();
+    line_ranges
+        .iter()
+        .map(|r| r.clone().all(|l| candidates.contains(&l)))
+        .collect()
+}
 
-        let new_content = new_lines.join("\n") + "\n";
-        fs::write(&file_path, new_content)
-            .with_context(|| format!("Failed to write file: {}", file_path.display()))?;
+// The `/* ... */` comment wrapping a statement disabled on its own
+fn block_comment_around(
+    content: &str,
+    m: &Match,
+    open: &str,
+    close: &str,
+) -> Option<(usize, usize)> {
+    let before = content[..m.start_byte].trim_end_matches([' ', '\t', '\r', '\n']);
+    let before = before.strip_suffix(DISABLED_TAG).unwrap_or(before);
+    let after = &content[m.end_byte..];
+    let after_trimmed = after.trim_start_matches([' ', '\t', '\r', '\n']);
+    if !before.ends_with(open) || !after_trimmed.starts_with(close) {
+        return None;
     }
+    Some((
+        before.len() - open.len(),
+        m.end_byte + after.len() - after_trimmed.len() + close.len(),
+    ))
+}
 
-    Ok(())
+// The `//` comment holding a statement, from the marker to the end of its last
+// line, or just the statement where other statements share the line
+fn line_comment_around(
+    content: &str,
+    lines: &LineIndex,
+    m: &Match,
+    marker: &str,
+) -> Option<(usize, usize)> {
+    let line_start = lines.line_start(m.start_byte);
+    let prefix = content[line_start..m.start_byte].trim_start();
+    let rest = prefix.strip_prefix(marker)?;
+    let rest = rest.strip_prefix(DISABLED_TAG).unwrap_or(rest);
+    let start = if rest.trim().is_empty() {
+        m.start_byte - prefix.len()
+    } else {
+        m.start_byte
+    };
+    let line_end = lines.line_range(m.end_line_number).end;
+    let end = if content[m.end_byte..line_end].trim().is_empty() {
+        line_end
+    } else {
+        m.end_byte
+    };
+    Some((start, end))
 }
 
-fn comment_line(line: &str) -> String {
-    // Find the first non-whitespace character and insert // before it
-    let trimmed = line.trim_start();
-    let leading_whitespace = &line[..line.len() - trimmed.len()];
-    format!("{}// {}", leading_whitespace, trimmed)
+// Apply the edits, returning the result along with the range each applied edit
+// replaced and the range of its replacement in the result
+fn apply_edits(content: &str, mut edits: Vec<Edit>) -> (String, Vec<EditRange>) {
+    edits.sort_by_key(|e| (e.start, e.end));
+    let mut result = String::with_capacity(content.len());
+    let mut ranges = Vec::new();
+    let mut pos = 0;
+    for edit in edits {
+        // Overlapping edits (a statement selected twice) are applied once
+        if edit.start < pos {
+            continue;
+        }
+        result.push_str(&content[pos..edit.start]);
+        let start = result.len();
+        result.push_str(&edit.text);
+        ranges.push((edit.start..edit.end, start..result.len()));
+        pos = edit.end;
+    }
+    result.push_str(&content[pos..]);
+    (result, ranges)
 }
 
-fn uncomment_line(line: &str) -> String {
-    // Remove the // comment marker
-    let re = Regex::new(r"^(\s*)//\s*(.*)$").unwrap();
-    if let Some(caps) = re.captures(line) {
-        format!("{}{}", &caps[1], &caps[2])
-    } else {
-        line.to_string()
+fn comment_line(line: &str, marker: &str) -> String {
+    // Insert the comment marker before the first non-blank character and keep the
+    // rest of the line as is, so that `uncomment_line` restores it exactly
+    let trimmed = line.trim_start_matches([' ', '\t']);
+    let leading_whitespace = &line[..line.len() - trimmed.len()];
+    if trimmed.is_empty() {
+        // No trailing blank on empty lines
+        return format!("{}{}{}", leading_whitespace, marker, DISABLED_TAG);
     }
+    format!(
+        "{}{}{} {}",
+        leading_whitespace, marker, DISABLED_TAG, trimmed
+    )
+}
+
+fn uncomment_line(line: &str, marker: &str) -> String {
+    let trimmed = line.trim_start_matches([' ', '\t']);
+    let leading_whitespace = &line[..line.len() - trimmed.len()];
+    let Some(rest) = trimmed.strip_prefix(marker) else {
+        return line.to_string();
+    };
+    let rest = match rest.strip_prefix(DISABLED_TAG) {
+        // Exact inverse of `comment_line`
+        Some(rest) => rest.strip_prefix(' ').unwrap_or(rest),
+        // Commented out by hand: drop the blanks after the marker
+        None => rest.trim_start_matches([' ', '\t']),
+    };
+    format!("{}{}", leading_whitespace, rest)
 }
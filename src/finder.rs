@@ -1,313 +1,716 @@
-use anyhow::{Context, Result};
-use regex::Regex;
+use anyhow::{bail, Context, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use rayon::prelude::*;
+use regex::{Captures, Regex};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::Path;
-use walkdir::WalkDir;
-
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+
+use crate::cli::{KeywordOptions, ScanOptions, WalkOptions};
+use crate::config::{Config, LanguageConfig};
+use crate::git;
+use crate::language::{self, Language, StatementEnd, DISABLED_TAG};
+use crate::lexer::{
+    find_closing, find_statement_end, non_code_spans, span_at, statement_position, Position,
+    TokenKind,
+};
+use crate::line_index::LineIndex;
+use crate::scope::{function_at, function_scopes};
+use crate::source::{self, Content, Decoding, SourceFile};
 use crate::types::Match;
+use encoding_rs::UTF_8;
 
-pub fn find_debug_printfs(
-    path: &Path,
-    find_commented: bool,
-    detect_all: bool,
-) -> Result<Vec<Match>> {
-    let mut matches = Vec::new();
+// An output pattern compiled for scanning
+pub(crate) struct StatementPattern {
+    pub(crate) end: StatementEnd,
+    pub(crate) requires_keyword: bool,
+}
 
-    // Pattern to match C printf-like functions (multiline support with (?s))
-    // Match from function name to closing paren, then optional whitespace and semicolon
-    let c_functions_pattern = if detect_all {
-        // Match all output functions regardless of content
-        Regex::new(
-            r"(?s)(printf|fprintf|sprintf|snprintf|printf_debug|dprintf|puts|fputs|fputc|putchar|fputchar|write|perror)\s*\([^)]*\)\s*;",
-        )?
-    } else {
-        // Match only those with "debug" or "DEBUG"
-        Regex::new(
-            r"(?s)(printf|fprintf|sprintf|snprintf|printf_debug|dprintf|puts|fputs|fputc|putchar|fputchar|write|perror)\s*\([^)]*?(debug|DEBUG)[^)]*\)\s*;",
-        )?
-    };
+// The compiled output patterns of one language
+pub(crate) struct Scanner {
+    pub(crate) language: &'static dyn Language,
+    pub(crate) patterns: Vec<StatementPattern>,
+    // All heads in one alternation so each file is searched in a single pass
+    pub(crate) heads: Regex,
+    // Capture group of each pattern within `heads`
+    groups: Vec<usize>,
+}
 
-    // Pattern to match C++ streams (multiline support with (?s))
-    let cpp_stream_pattern = if detect_all {
-        // Match all stream output regardless of content
-        Regex::new(r"(?s)(std::cout|std::cerr|std::clog)\s*<<[^;]*?;")?
-    } else {
-        // Match only those with "debug" or "DEBUG"
-        Regex::new(r"(?s)(std::cout|std::cerr|std::clog)\s*<<[^;]*?(debug|DEBUG)[^;]*?;")?
-    };
+impl Scanner {
+    fn new(language: &'static dyn Language, extra: Option<&LanguageConfig>) -> Result<Self> {
+        let mut sources: Vec<(Cow<str>, StatementEnd, bool)> = language
+            .output_patterns()
+            .iter()
+            .map(|p| (Cow::Borrowed(p.head), p.end, p.requires_keyword))
+            .collect();
+        if let Some(extra) = extra {
+            for name in &extra.functions {
+                sources.push((function_head(name).into(), StatementEnd::Call, true));
+            }
+            for name in &extra.debug_functions {
+                sources.push((function_head(name).into(), StatementEnd::Call, false));
+            }
+        }
+
+        let patterns: Vec<StatementPattern> = sources
+            .iter()
+            .map(|&(_, end, requires_keyword)| StatementPattern {
+                end,
+                requires_keyword,
+            })
+            .collect();
+
+        let heads = sources
+            .iter()
+            .enumerate()
+            .map(|(i, (head, _, _))| format!("(?P<p{}>{})", i, head))
+            .collect::<Vec<_>>()
+            .join("|");
+        let heads = Regex::new(&heads)?;
+
+        let names: Vec<_> = heads.capture_names().collect();
+        let groups = (0..patterns.len())
+            .map(|i| {
+                let name = format!("p{}", i);
+                names
+                    .iter()
+                    .position(|n| *n == Some(name.as_str()))
+                    .expect("every pattern has a named group")
+            })
+            .collect();
+
+        Ok(Self {
+            language,
+            patterns,
+            heads,
+            groups,
+        })
+    }
+
+    // Find which pattern matched a head found by `heads`
+    pub(crate) fn matched_pattern<'h>(
+        &self,
+        caps: &Captures<'h>,
+    ) -> Option<(&StatementPattern, regex::Match<'h>)> {
+        self.patterns
+            .iter()
+            .zip(&self.groups)
+            .find_map(|(p, &group)| caps.get(group).map(|head| (p, head)))
+    }
+}
+
+// Head pattern of a configured output function; `name!` is a Rust macro
+fn function_head(name: &str) -> String {
+    match name.strip_suffix('!') {
+        Some(mac) => format!(r"\b{}!\s*[(\[{{]", regex::escape(mac)),
+        None => format!(r"\b{}\s*\(", regex::escape(name)),
+    }
+}
+
+// Built-in patterns are compiled once and shared by every scan
+static DEFAULT_SCANNERS: OnceLock<Arc<[Scanner]>> = OnceLock::new();
+
+fn default_scanners() -> Result<Arc<[Scanner]>> {
+    if let Some(scanners) = DEFAULT_SCANNERS.get() {
+        return Ok(scanners.clone());
+    }
+
+    let scanners = language::all()
+        .iter()
+        .map(|&lang| Scanner::new(lang, None))
+        .collect::<Result<_>>()?;
+    Ok(DEFAULT_SCANNERS.get_or_init(|| scanners).clone())
+}
+
+// Everything that applies to the files of one directory
+struct Settings {
+    config: Config,
+    // Exclude globs of the configs of this directory and its ancestors
+    excludes: Vec<Gitignore>,
+    // Configured extensions in addition to the built-in ones
+    extensions: HashMap<String, &'static dyn Language>,
+    scanners: Arc<[Scanner]>,
+    // Keywords required by --debug
+    keywords: Regex,
+}
 
-    // Pattern to match Rust macros (multiline support with (?s))
-    // Match macro_name!(...); where ... can contain anything except unbalanced parens
-    let rust_macro_pattern = if detect_all {
-        // Match all Rust output macros regardless of content
-        Regex::new(r"(?s)(println!|eprintln!|print!|eprint!|dbg!)\s*\([^)]*\)\s*;")?
+impl Settings {
+    fn new(config: Config, excludes: Vec<Gitignore>, options: &KeywordOptions) -> Result<Self> {
+        let mut extensions = HashMap::new();
+        for &lang in language::all() {
+            if let Some(extra) = config.languages.get(lang.name()) {
+                for ext in &extra.extensions {
+                    extensions.insert(ext.trim_start_matches('.').to_string(), lang);
+                }
+            }
+        }
+
+        let has_functions = config
+            .languages
+            .values()
+            .any(|l| !l.functions.is_empty() || !l.debug_functions.is_empty());
+        let scanners = if has_functions {
+            language::all()
+                .iter()
+                .map(|&lang| Scanner::new(lang, config.languages.get(lang.name())))
+                .collect::<Result<_>>()?
+        } else {
+            default_scanners()?
+        };
+
+        // --keyword replaces the configured keywords
+        let patterns = if !options.keyword.is_empty() {
+            &options.keyword
+        } else {
+            &config.keywords
+        };
+        let ignore_case = options.ignore_case || config.ignore_case == Some(true);
+        let keywords = keyword_regex(patterns, ignore_case)?;
+
+        Ok(Self {
+            config,
+            excludes,
+            extensions,
+            scanners,
+            keywords,
+        })
+    }
+
+    fn language_for(&self, path: &Path) -> Option<&'static dyn Language> {
+        language::for_path(path).or_else(|| {
+            let ext = path.extension()?.to_str()?;
+            self.extensions.get(ext).copied()
+        })
+    }
+
+    fn scanner_for(&self, lang: &dyn Language) -> &Scanner {
+        self.scanners
+            .iter()
+            .find(|s| s.language.name() == lang.name())
+            .expect("every registered language has a scanner")
+    }
+
+    // `path` must be absolute, like the roots of the exclude globs
+    fn is_excluded(&self, path: &Path) -> bool {
+        self.excludes
+            .iter()
+            .any(|gi| gi.matched_path_or_any_parents(path, false).is_ignore())
+    }
+}
+
+// Combine keyword regexes into one, defaulting to `debug|DEBUG`
+fn keyword_regex(patterns: &[String], ignore_case: bool) -> Result<Regex> {
+    let pattern = if patterns.is_empty() {
+        "debug|DEBUG".to_string()
     } else {
-        // Match dbg! always, and other macros only if they contain "debug" or "DEBUG"
-        Regex::new(
-            r"(?s)dbg!\s*\([^)]*\)\s*;|(println!|eprintln!|print!|eprint!)\s*\([^)]*?(debug|DEBUG)[^)]*\)\s*;",
-        )?
+        for p in patterns {
+            Regex::new(p).with_context(|| format!("Invalid keyword pattern: {}", p))?;
+        }
+        patterns
+            .iter()
+            .map(|p| format!("(?:{})", p))
+            .collect::<Vec<_>>()
+            .join("|")
     };
-
-    // Pattern to match Java output statements (multiline support with (?s))
-    let java_pattern = if detect_all {
-        // Match all Java output statements regardless of content
-        Regex::new(
-            r"(?s)(System\.out\.println|System\.out\.printf|System\.out\.print|System\.err\.println|System\.err\.printf|System\.err\.print)\s*\([^)]*\)\s*;",
-        )?
+    let pattern = if ignore_case {
+        format!("(?i:{})", pattern)
     } else {
-        // Match only those with "debug" or "DEBUG"
-        Regex::new(
-            r"(?s)(System\.out\.println|System\.out\.printf|System\.out\.print|System\.err\.println|System\.err\.printf|System\.err\.print)\s*\([^)]*?(debug|DEBUG)[^)]*\)\s*;",
-        )?
+        pattern
     };
+    Ok(Regex::new(&pattern)?)
+}
 
-    // Pattern to match Go output statements (multiline support with (?s))
-    // Go doesn't require semicolons, so we match just the closing paren
-    let go_pattern = if detect_all {
-        // Match all Go output statements regardless of content
-        Regex::new(
-            r"(?s)(fmt\.Println|fmt\.Printf|fmt\.Print|fmt\.Fprintln|fmt\.Fprintf|fmt\.Fprint|log\.Println|fmt\.Printf|log\.Print|log\.Fatal|log\.Fatalf|log\.Fatalln|log\.Panic|log\.Panicf|log\.Panicln)\s*\([^)]*\)",
-        )?
-    } else {
-        // Match only those with "debug" or "DEBUG"
-        Regex::new(
-            r"(?s)(fmt\.Println|fmt\.Printf|fmt\.Print|fmt\.Fprintln|fmt\.Fprintf|fmt\.Fprint|log\.Println|log\.Printf|log\.Print|log\.Fatal|log\.Fatalf|log\.Fatalln|log\.Panic|log\.Panicf|log\.Panicln)\s*\([^)]*?(debug|DEBUG)[^)]*\)",
-        )?
+// Finds the settings of each directory from the .flop.toml files above it
+#[derive(Default)]
+struct ConfigResolver {
+    keywords: KeywordOptions,
+    // Keyed by canonical directory
+    settings: HashMap<PathBuf, Arc<Settings>>,
+    // Walked directory to canonical directory
+    canonical: HashMap<PathBuf, PathBuf>,
+}
+
+impl ConfigResolver {
+    // Settings for a file or directory, along with its canonical directory
+    fn settings_for(&mut self, path: &Path) -> Result<(Arc<Settings>, PathBuf)> {
+        let dir = if path.is_dir() {
+            path
+        } else {
+            match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => Path::new("."),
+            }
+        };
+
+        let canonical = match self.canonical.get(dir) {
+            Some(canonical) => canonical.clone(),
+            None => {
+                let canonical = fs::canonicalize(dir)
+                    .with_context(|| format!("Failed to resolve path: {}", dir.display()))?;
+                self.canonical.insert(dir.to_path_buf(), canonical.clone());
+                canonical
+            }
+        };
+
+        Ok((self.settings_for_dir(&canonical)?, canonical))
+    }
+
+    fn settings_for_dir(&mut self, dir: &Path) -> Result<Arc<Settings>> {
+        if let Some(settings) = self.settings.get(dir) {
+            return Ok(settings.clone());
+        }
+
+        let parent = match dir.parent() {
+            Some(parent) => Some(self.settings_for_dir(parent)?),
+            None => None,
+        };
+
+        let settings = match (Config::load(dir)?, parent) {
+            (None, Some(parent)) => parent,
+            (own, parent) => {
+                let own = own.unwrap_or_default();
+                let (config, mut excludes) = match &parent {
+                    Some(parent) => (parent.config.merge(&own), parent.excludes.clone()),
+                    None => (Config::default().merge(&own), Vec::new()),
+                };
+                if !own.exclude.is_empty() {
+                    let mut builder = GitignoreBuilder::new(dir);
+                    for glob in &own.exclude {
+                        builder.add_line(None, glob)?;
+                    }
+                    excludes.push(builder.build()?);
+                }
+                Arc::new(Settings::new(config, excludes, &self.keywords)?)
+            }
+        };
+
+        self.settings.insert(dir.to_path_buf(), settings.clone());
+        Ok(settings)
+    }
+}
+
+// Build and vendored dependency directories that are never searched
+const SKIPPED_DIRS: &[&str] = &[".git", "target", "vendor", "node_modules", "build"];
+
+// A file that could not be decoded, with the reason
+pub struct Unreadable {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+pub fn find_debug_printfs(
+    path: &Path,
+    find_commented: bool,
+    detect_all: bool,
+    scan: &ScanOptions,
+) -> Result<Vec<Match>> {
+    let (matches, unreadable) = find_matches(path, find_commented, detect_all, scan)?;
+    report_unreadable(&unreadable, scan)?;
+    Ok(matches)
+}
+
+// Warn about the files that could not be decoded, or stop on them with
+// --strict-encoding
+pub fn report_unreadable(unreadable: &[Unreadable], scan: &ScanOptions) -> Result<()> {
+    if unreadable.is_empty() {
+        return Ok(());
+    }
+    // Another encoding only helps if none was given
+    let hint = match scan.encoding.encoding {
+        None => "; use --encoding (e.g. --encoding auto) to read them",
+        Some(_) => "",
     };
+    if scan.encoding.strict_encoding {
+        let files: Vec<String> = unreadable
+            .iter()
+            .map(|u| format!("  {}: {}", u.path.display(), u.reason))
+            .collect();
+        bail!(
+            "Cannot decode {} file(s){}:\n{}",
+            unreadable.len(),
+            hint,
+            files.join("\n")
+        );
+    }
 
-    let comment_pattern = Regex::new(r"^\s*//")?;
+    let mut reasons: BTreeMap<&str, usize> = BTreeMap::new();
+    for u in unreadable {
+        eprintln!("Warning: skipping {}: {}", u.path.display(), u.reason);
+        *reasons.entry(&u.reason).or_default() += 1;
+    }
+    let reasons: Vec<String> = reasons
+        .iter()
+        .map(|(reason, count)| format!("{} {}", count, reason))
+        .collect();
+    eprintln!(
+        "Skipped {} file(s) that cannot be decoded ({}){}",
+        unreadable.len(),
+        reasons.join(", "),
+        hint
+    );
+    Ok(())
+}
 
-    let entries: Vec<_> = if path.is_file() {
-        vec![path.to_path_buf()]
-    } else {
-        WalkDir::new(path)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| {
-                e.path().is_file()
-                    && e.path()
-                        .extension()
-                        .and_then(|s| s.to_str())
-                        .map(|ext| {
-                            matches!(
-                                ext,
-                                "c" | "h" | "cpp" | "hpp" | "cc" | "cxx" | "rs" | "java" | "go"
-                            )
-                        })
-                        .unwrap_or(false)
-            })
-            .map(|e| e.path().to_path_buf())
-            .collect()
+// Find statements along with the files that could not be decoded
+pub fn find_matches(
+    path: &Path,
+    find_commented: bool,
+    detect_all: bool,
+    scan: &ScanOptions,
+) -> Result<(Vec<Match>, Vec<Unreadable>)> {
+    let walk = &scan.walk;
+    let decoding = Decoding::from_label(scan.encoding.encoding.as_deref())?;
+    let mut resolver = ConfigResolver {
+        keywords: scan.keywords.clone(),
+        ..Default::default()
     };
+    let (root_settings, root_dir) = resolver.settings_for(path)?;
+    let added = git::added_lines(path, &scan.diff)?;
 
-    for file_path in entries {
-        let content = fs::read_to_string(&file_path)
-            .with_context(|| format!("Failed to read file: {}", file_path.display()))?;
-
-        // Find all C-style function calls
-        for cap in c_functions_pattern.find_iter(&content) {
-            let match_str = cap.as_str();
-            let start_offset = cap.start();
-            let end_offset = cap.end();
-
-            // Calculate line numbers from byte offsets
-            // Count newlines before the start position, then add 1
-            let line_number = content[..start_offset].matches('\n').count() + 1;
-            // For end line, count newlines up to the end position
-            let end_line_number = content[..end_offset].matches('\n').count() + 1;
-
-            // Get the line content (for display purposes, we'll get the first line of the match)
-            let line_start_offset = content[..start_offset]
-                .rfind('\n')
-                .map(|pos| pos + 1)
-                .unwrap_or(0);
-            let line_content = content[line_start_offset..]
-                .lines()
-                .next()
-                .unwrap_or("")
-                .to_string();
-
-            // Check if commented (check the beginning of the statement)
-            let is_commented = comment_pattern.is_match(&line_content);
-
-            if is_commented == find_commented {
-                // Extract original lines for multiline display
-                let multiline_content: Vec<String> =
-                    match_str.lines().map(|s| s.to_string()).collect();
-
-                matches.push(Match {
-                    file_path: file_path.clone(),
-                    line_number,
-                    end_line_number,
-                    line_content: match_str.replace('\n', " ").trim().to_string(),
-                    multiline_content,
-                });
+    // Files along with their absolute path
+    let mut entries: Vec<(PathBuf, Arc<Settings>, PathBuf)> = if path.is_file() {
+        let absolute = root_dir.join(path.file_name().unwrap_or_default());
+        vec![(path.to_path_buf(), root_settings, absolute)]
+    } else {
+        let hidden = walk.hidden || root_settings.config.hidden == Some(true);
+        let mut entries = Vec::new();
+        for file_path in walk_files(path, walk, hidden)? {
+            let (settings, dir) = resolver.settings_for(&file_path)?;
+            let absolute = dir.join(file_path.file_name().unwrap_or_default());
+            if settings.language_for(&file_path).is_some() && !settings.is_excluded(&absolute) {
+                entries.push((file_path, settings, absolute));
             }
         }
+        entries
+    };
+    if let Some(added) = &added {
+        entries.retain(|(_, _, absolute)| added.contains_key(absolute));
+    }
+    // Walk order depends on the file system; sort for deterministic output
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+    // Scan files in parallel; collecting keeps the results in file order
+    let per_file = entries
+        .par_iter()
+        .map(|(file_path, settings, _)| {
+            let source = match SourceFile::read(file_path, decoding)? {
+                Content::Text(source) => source,
+                Content::Binary => return Ok(FileScan::Matches(Vec::new())),
+                Content::Unreadable(reason) => return Ok(FileScan::Unreadable(reason)),
+            };
+
+            // Files passed explicitly may have any extension; scan them as C
+            let lang = settings.language_for(file_path).unwrap_or(&language::c::C);
+            let mut file_matches = scan_source(
+                file_path,
+                &source.text,
+                settings,
+                lang,
+                find_commented,
+                detect_all,
+                scan.any_comment,
+            )?;
+
+            // Edits are written back in the encoding the file was read in, and
+            // only if the file did not change since
+            let file_hash = source::content_hash(&source.text);
+            for m in &mut file_matches {
+                m.language = lang.name();
+                m.commented = find_commented;
+                m.encoding = source.encoding();
+                m.file_hash = file_hash;
+            }
+            Ok(FileScan::Matches(file_matches))
+        })
+        .collect::<Result<Vec<_>>>()?;
 
-        // Find all C++ stream operations
-        for cap in cpp_stream_pattern.find_iter(&content) {
-            let match_str = cap.as_str();
-            let start_offset = cap.start();
-            let end_offset = cap.end();
-
-            // Calculate line numbers from byte offsets
-            // Count newlines before the start position, then add 1
-            let line_number = content[..start_offset].matches('\n').count() + 1;
-            // For end line, count newlines up to the end position
-            let end_line_number = content[..end_offset].matches('\n').count() + 1;
-
-            // Get the line content
-            let line_start_offset = content[..start_offset]
-                .rfind('\n')
-                .map(|pos| pos + 1)
-                .unwrap_or(0);
-            let line_content = content[line_start_offset..]
-                .lines()
-                .next()
-                .unwrap_or("")
-                .to_string();
-
-            // Check if commented
-            let is_commented = comment_pattern.is_match(&line_content);
-
-            if is_commented == find_commented {
-                // Extract original lines for multiline display
-                let multiline_content: Vec<String> =
-                    match_str.lines().map(|s| s.to_string()).collect();
-
-                matches.push(Match {
-                    file_path: file_path.clone(),
-                    line_number,
-                    end_line_number,
-                    line_content: match_str.replace('\n', " ").trim().to_string(),
-                    multiline_content,
+    let mut matches = Vec::new();
+    let mut unreadable = Vec::new();
+    for ((file_path, _, absolute), scanned) in entries.iter().zip(per_file) {
+        let mut file_matches = match scanned {
+            FileScan::Matches(file_matches) => file_matches,
+            FileScan::Unreadable(reason) => {
+                unreadable.push(Unreadable {
+                    path: file_path.clone(),
+                    reason,
                 });
+                continue;
             }
+        };
+        // Keep only statements on lines added according to git
+        if let Some(ranges) = added.as_ref().and_then(|a| a.get(absolute)) {
+            file_matches.retain(|m| git::is_added(ranges, m.line_number, m.end_line_number));
         }
+        matches.extend(file_matches);
+    }
 
-        // Find all Rust macro calls
-        for cap in rust_macro_pattern.find_iter(&content) {
-            let match_str = cap.as_str();
-            let start_offset = cap.start();
-            let end_offset = cap.end();
-
-            // Calculate line numbers from byte offsets
-            let line_number = content[..start_offset].matches('\n').count() + 1;
-            let end_line_number = content[..end_offset].matches('\n').count() + 1;
-
-            // Get the line content
-            let line_start_offset = content[..start_offset]
-                .rfind('\n')
-                .map(|pos| pos + 1)
-                .unwrap_or(0);
-            let line_content = content[line_start_offset..]
-                .lines()
-                .next()
-                .unwrap_or("")
-                .to_string();
-
-            // Check if commented
-            let is_commented = comment_pattern.is_match(&line_content);
-
-            if is_commented == find_commented {
-                // Extract original lines for multiline display
-                let multiline_content: Vec<String> =
-                    match_str.lines().map(|s| s.to_string()).collect();
-
-                matches.push(Match {
-                    file_path: file_path.clone(),
-                    line_number,
-                    end_line_number,
-                    line_content: match_str.replace('\n', " ").trim().to_string(),
-                    multiline_content,
-                });
+    Ok((matches, unreadable))
+}
+
+// Outcome of scanning one file
+enum FileScan {
+    Matches(Vec<Match>),
+    Unreadable(String),
+}
+
+// Find the output statements of one decoded file
+fn scan_source(
+    file_path: &Path,
+    content: &str,
+    settings: &Settings,
+    lang: &'static dyn Language,
+    find_commented: bool,
+    detect_all: bool,
+    any_comment: bool,
+) -> Result<Vec<Match>> {
+    let scanner = settings.scanner_for(lang);
+    let require_keyword = !detect_all || settings.config.debug == Some(true);
+
+    // Most files have no output statements at all
+    if !scanner.heads.is_match(content) {
+        return Ok(Vec::new());
+    }
+
+    // Active statements come from the syntax tree; commented ones are not
+    // part of it, so they are still found by the lexer
+    #[cfg(feature = "tree-sitter")]
+    if !find_commented {
+        return crate::syntax::find_statements(
+            file_path,
+            content,
+            scanner,
+            &settings.keywords,
+            require_keyword,
+        );
+    }
+
+    Ok(scan_file(
+        file_path,
+        content,
+        scanner,
+        find_commented,
+        any_comment,
+        &settings.keywords,
+        require_keyword,
+    ))
+}
+
+// Collect the files under `path`, honoring ignore files and walk options
+fn walk_files(path: &Path, walk: &WalkOptions, hidden: bool) -> Result<Vec<PathBuf>> {
+    // Included files are filtered after the walk: as overrides, they would also
+    // bring back the files listed in .gitignore
+    let mut include = OverrideBuilder::new(path);
+    for glob in &walk.include {
+        include.add(glob)?;
+    }
+    let include = include.build()?;
+    let mut exclude = OverrideBuilder::new(path);
+    for glob in &walk.exclude {
+        exclude.add(&format!("!{}", glob))?;
+    }
+
+    let files = WalkBuilder::new(path)
+        .hidden(!hidden)
+        // .gitignore is honored even outside of a git repository
+        .require_git(false)
+        .add_custom_ignore_filename(".flopignore")
+        .overrides(exclude.build()?)
+        .filter_entry(|e| {
+            let is_dir = e.file_type().is_some_and(|t| t.is_dir());
+            !(is_dir && e.depth() > 0 && SKIPPED_DIRS.iter().any(|d| e.file_name() == *d))
+        })
+        .build()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_some_and(|t| t.is_file()))
+        .filter(|e| walk.include.is_empty() || include.matched(e.path(), false).is_whitelist())
+        .map(|e| e.into_path())
+        .collect();
+
+    Ok(files)
+}
+
+// Find the output statements of one file with the lexer
+fn scan_file(
+    file_path: &Path,
+    content: &str,
+    scanner: &Scanner,
+    find_commented: bool,
+    any_comment: bool,
+    keywords: &Regex,
+    require_keyword: bool,
+) -> Vec<Match> {
+    let mut matches = Vec::new();
+    let dialect = scanner.language.dialect();
+    let spans = non_code_spans(content, dialect);
+    let lines = LineIndex::new(content);
+    let scopes = function_scopes(content, &spans, dialect);
+
+    for caps in scanner.heads.captures_iter(content) {
+        let Some((pattern, head)) = scanner.matched_pattern(&caps) else {
+            continue;
+        };
+        let start_offset = head.start();
+
+        // Get the line start offset
+        let line_start_offset = lines.line_start(start_offset);
+
+        // A statement is commented if it sits in a `//` comment that starts the line,
+        // or is alone in a `/* */` comment. Heads inside strings, doc comments or
+        // trailing comments are not statements.
+        let (is_commented, block) = match span_at(&spans, start_offset) {
+            None => (false, None),
+            Some(span)
+                if span.kind == TokenKind::LineComment
+                    && content[line_start_offset..span.start].trim().is_empty()
+                    && is_disabled(&content[span.start + 2..start_offset], true, any_comment) =>
+            {
+                (true, None)
             }
+            Some(span)
+                if span.kind == TokenKind::BlockComment
+                    && is_disabled(&content[span.start + 2..start_offset], false, any_comment) =>
+            {
+                (true, Some(*span))
+            }
+            Some(_) => continue,
+        };
+        // Only statements in `//` comments have a comment marker on every line
+        let line_commented = is_commented && block.is_none();
+
+        if is_commented != find_commented {
+            continue;
         }
 
-        // Find all Java output statements
-        for cap in java_pattern.find_iter(&content) {
-            let match_str = cap.as_str();
-            let start_offset = cap.start();
-            let end_offset = cap.end();
-
-            // Calculate line numbers from byte offsets
-            let line_number = content[..start_offset].matches('\n').count() + 1;
-            let end_line_number = content[..end_offset].matches('\n').count() + 1;
-
-            // Get the line content
-            let line_start_offset = content[..start_offset]
-                .rfind('\n')
-                .map(|pos| pos + 1)
-                .unwrap_or(0);
-            let line_content = content[line_start_offset..]
-                .lines()
-                .next()
-                .unwrap_or("")
-                .to_string();
-
-            // Check if commented
-            let is_commented = comment_pattern.is_match(&line_content);
-
-            if is_commented == find_commented {
-                // Extract original lines for multiline display
-                let multiline_content: Vec<String> =
-                    match_str.lines().map(|s| s.to_string()).collect();
-
-                matches.push(Match {
-                    file_path: file_path.clone(),
-                    line_number,
-                    end_line_number,
-                    line_content: match_str.replace('\n', " ").trim().to_string(),
-                    multiline_content,
-                });
+        // Output used as a value (`int n = printf(...)`) is not a statement. Lines
+        // commented out with `//` are taken as they are.
+        let position = if line_commented {
+            Position::Block
+        } else {
+            let statement_start = block.map_or(start_offset, |block| block.start);
+            match statement_position(content, &spans, statement_start, dialect) {
+                Some(position) => position,
+                None => continue,
+            }
+        };
+
+        let end_offset = match pattern.end {
+            StatementEnd::Call => {
+                let open = head.end() - 1;
+                let Some(close) = find_closing(content, open, dialect, line_commented) else {
+                    continue;
+                };
+                let after = close + content[close..].len() - content[close..].trim_start().len();
+                match scanner.language.terminator() {
+                    Some(t) if content[after..].starts_with(t) => after + t.len_utf8(),
+                    // Brace-delimited macros are statements on their own
+                    Some(_) if content.as_bytes()[open] != b'{' => continue,
+                    _ => close,
+                }
+            }
+            StatementEnd::Terminator => {
+                let Some(terminator) = scanner.language.terminator() else {
+                    continue;
+                };
+                match find_statement_end(content, head.end(), dialect, terminator, line_commented) {
+                    Some(end) => end,
+                    None => continue,
+                }
+            }
+        };
+
+        // The statement must fill its `/* */` comment
+        if let Some(block) = block {
+            let inner_end = block.end - 2;
+            if !content[block.start..block.end].ends_with("*/")
+                || end_offset > inner_end
+                || !content[end_offset..inner_end].trim().is_empty()
+            {
+                continue;
             }
         }
 
-        // Find all Go output statements
-        for cap in go_pattern.find_iter(&content) {
-            let match_str = cap.as_str();
-            let start_offset = cap.start();
-            let end_offset = cap.end();
-
-            // Calculate line numbers from byte offsets
-            let line_number = content[..start_offset].matches('\n').count() + 1;
-            let end_line_number = content[..end_offset].matches('\n').count() + 1;
-
-            // Get the line content
-            let line_start_offset = content[..start_offset]
-                .rfind('\n')
-                .map(|pos| pos + 1)
-                .unwrap_or(0);
-            let line_content = content[line_start_offset..]
-                .lines()
-                .next()
-                .unwrap_or("")
-                .to_string();
-
-            // Check if commented
-            let is_commented = comment_pattern.is_match(&line_content);
-
-            if is_commented == find_commented {
-                // Extract original lines for multiline display
-                let multiline_content: Vec<String> =
-                    match_str.lines().map(|s| s.to_string()).collect();
-
-                matches.push(Match {
-                    file_path: file_path.clone(),
-                    line_number,
-                    end_line_number,
-                    line_content: match_str.replace('\n', " ").trim().to_string(),
-                    multiline_content,
-                });
-            }
+        let match_str = &content[start_offset..end_offset];
+
+        if require_keyword && pattern.requires_keyword && !keywords.is_match(match_str) {
+            continue;
         }
+
+        let mut m = statement_match(
+            file_path,
+            content,
+            &lines,
+            start_offset,
+            end_offset,
+            function_at(&scopes, start_offset),
+            keywords,
+        );
+        m.control_body = position == Position::Body;
+        matches.push(m);
     }
 
-    // Remove duplicates based on file_path and line_number
-    // This can happen when the same line matches multiple patterns (e.g., printf in C and Java)
-    let mut seen = std::collections::HashSet::new();
-    matches.retain(|m| seen.insert((m.file_path.clone(), m.line_number)));
+    matches
+}
 
-    Ok(matches)
+// Whether the comment text between the opening `//` or `/*` and a statement head
+// marks a statement disabled by flop. With --any-comment, statements commented out
+// by hand count too.
+fn is_disabled(prefix: &str, line_comment: bool, any_comment: bool) -> bool {
+    // Doc comments (`///`, `//!`, `/**`, `/*!`) hold documentation, not disabled code
+    if prefix.starts_with(['/', '!', '*']) {
+        return false;
+    }
+    match prefix.strip_prefix(DISABLED_TAG) {
+        // `flop on` comments out whole lines, with every statement on them
+        Some(_) if line_comment => true,
+        Some(rest) => rest.trim().is_empty(),
+        // Text before the statement is only allowed in a line comment, as before
+        None => any_comment && (line_comment || prefix.trim().is_empty()),
+    }
+}
+
+// Build a match for the statement spanning `start..end` in `content`
+pub(crate) fn statement_match(
+    file_path: &Path,
+    content: &str,
+    lines: &LineIndex,
+    start: usize,
+    end: usize,
+    function_name: Option<String>,
+    keywords: &Regex,
+) -> Match {
+    let match_str = &content[start..end];
+
+    let mut found: Vec<String> = Vec::new();
+    for k in keywords.find_iter(match_str) {
+        if !k.is_empty() && !found.iter().any(|f| f == k.as_str()) {
+            found.push(k.as_str().to_string());
+        }
+    }
+
+    // Extract original lines for multiline display
+    let multiline_content: Vec<String> = match_str.lines().map(|s| s.to_string()).collect();
+
+    Match {
+        file_path: file_path.to_path_buf(),
+        line_number: lines.line_number(start),
+        end_line_number: lines.line_number(end),
+        start_byte: start,
+        end_byte: end,
+        start_column: start - lines.line_start(start) + 1,
+        end_column: end - lines.line_start(end) + 1,
+        line_content: match_str.replace('\n', " ").trim().to_string(),
+        multiline_content,
+        function_name,
+        parent_kind: None,
+        control_body: false,
+        keywords: found,
+        language: "",
+        commented: false,
+        encoding: UTF_8,
+        file_hash: 0,
+    }
 }
@@ -0,0 +1,347 @@
+// Lines added in the local git repository, used by --changed, --staged and --since.
+// Diffs come from the git command line so that every repository layout
+// (worktrees, submodules, ...) behaves exactly like `git diff`. The options that
+// change the format of the output, like diff.noprefix or diff.relative, are
+// overridden.
+
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::cli::DiffOptions;
+
+// Added line ranges (1-based) of each changed file, keyed by absolute path
+pub type AddedLines = HashMap<PathBuf, Vec<RangeInclusive<usize>>>;
+
+// Changed lines of a file in a diff: `count` lines from `start` on each side. A
+// side without lines starts at the line before the change.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct DiffHunk {
+    old_start: usize,
+    old_count: usize,
+    new_start: usize,
+    new_count: usize,
+}
+
+const DIFF_ARGS: &[&str] = &[
+    "diff",
+    "--no-color",
+    "--no-ext-diff",
+    "--no-relative",
+    "--src-prefix=a/",
+    "--dst-prefix=b/",
+    "--unified=0",
+];
+
+// Collect the added lines selected by the diff options, or `None` when no diff
+// option was given and every line counts
+pub fn added_lines(path: &Path, diff: &DiffOptions) -> Result<Option<AddedLines>> {
+    let mut args = DIFF_ARGS.to_vec();
+    if diff.staged {
+        args.push("--cached");
+    } else if let Some(since) = &diff.since {
+        args.push(since);
+    } else if diff.changed {
+        args.push("HEAD");
+    } else {
+        return Ok(None);
+    }
+
+    let dir = dir_of(path);
+    let root = PathBuf::from(git(dir, &["rev-parse", "--show-toplevel"])?.trim_end());
+
+    let mut added = added_ranges(parse_diff(&root, &git(dir, &args)?));
+
+    if diff.staged {
+        // Lines of the index, while the files scanned are those on disk: follow
+        // the lines through the unstaged changes
+        let unstaged = parse_diff(&root, &git(dir, DIFF_ARGS)?);
+        for (file, ranges) in added.iter_mut() {
+            if let Some(hunks) = unstaged.get(file) {
+                *ranges = map_ranges(ranges, hunks);
+            }
+        }
+    } else {
+        // Untracked files are new in their entirety, but never part of the index
+        let untracked = git(
+            dir,
+            &["ls-files", "--others", "--exclude-standard", "--full-name"],
+        )?;
+        for file in untracked.lines() {
+            added.insert(root.join(file), vec![1..=usize::MAX]);
+        }
+    }
+
+    Ok(Some(added))
+}
+
+// Root of the git repository containing `path`, if any
+pub fn repository_root(path: &Path) -> Option<PathBuf> {
+    let root = git(dir_of(path), &["rev-parse", "--show-toplevel"]).ok()?;
+    Some(PathBuf::from(root.trim_end()))
+}
+
+// The directory itself, or the directory of a file
+fn dir_of(path: &Path) -> &Path {
+    if path.is_dir() {
+        return path;
+    }
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+// Whether any line of `start..=end` was added
+pub fn is_added(ranges: &[RangeInclusive<usize>], start: usize, end: usize) -> bool {
+    ranges
+        .iter()
+        .any(|r| *r.start() <= end && start <= *r.end())
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["-c", "core.quotePath=false"])
+        .args(args)
+        .output()
+        .context("Failed to run git")?;
+
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// Parse `git diff --unified=0` output into the hunks of each file
+fn parse_diff(root: &Path, diff: &str) -> HashMap<PathBuf, Vec<DiffHunk>> {
+    let mut hunks: HashMap<PathBuf, Vec<DiffHunk>> = HashMap::new();
+    let mut current: Option<PathBuf> = None;
+
+    for line in diff.lines() {
+        if let Some(file) = line.strip_prefix("+++ ") {
+            // Deleted files have no new side
+            current = unquote(file).strip_prefix("b/").map(|f| root.join(f));
+        } else if let Some(hunk) = line.strip_prefix("@@ ") {
+            let Some(file) = &current else {
+                continue;
+            };
+            // @@ -old[,count] +new[,count] @@
+            let mut sides = hunk.split_whitespace();
+            let old = sides.next().and_then(|s| s.strip_prefix('-'));
+            let new = sides.next().and_then(|s| s.strip_prefix('+'));
+            if let (Some((old_start, old_count)), Some((new_start, new_count))) =
+                (old.and_then(parse_side), new.and_then(parse_side))
+            {
+                hunks.entry(file.clone()).or_default().push(DiffHunk {
+                    old_start,
+                    old_count,
+                    new_start,
+                    new_count,
+                });
+            }
+        }
+    }
+
+    hunks
+}
+
+// Path of a `+++` line: quoted when it has special characters, and followed by a
+// tab when it has spaces
+fn unquote(file: &str) -> String {
+    let file = file.strip_suffix('\t').unwrap_or(file);
+    let Some(quoted) = file.strip_prefix('"').and_then(|f| f.strip_suffix('"')) else {
+        return file.to_string();
+    };
+    let mut unquoted = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        unquoted.push(match (c, c == '\\') {
+            (_, true) => match chars.next() {
+                Some('t') => '\t',
+                Some('n') => '\n',
+                Some(c) => c,
+                None => break,
+            },
+            (c, false) => c,
+        });
+    }
+    unquoted
+}
+
+// `start[,count]`, where the count defaults to 1
+fn parse_side(side: &str) -> Option<(usize, usize)> {
+    match side.split_once(',') {
+        Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+        None => Some((side.parse().ok()?, 1)),
+    }
+}
+
+fn added_ranges(hunks: HashMap<PathBuf, Vec<DiffHunk>>) -> AddedLines {
+    hunks
+        .into_iter()
+        .map(|(file, hunks)| {
+            let ranges = hunks
+                .iter()
+                .filter(|h| h.new_count > 0)
+                .map(|h| h.new_start..=h.new_start + h.new_count - 1)
+                .collect();
+            (file, ranges)
+        })
+        .filter(|(_, ranges): &(PathBuf, Vec<_>)| !ranges.is_empty())
+        .collect()
+}
+
+// Lines of the old side of a diff on its new side. Unchanged lines move with the
+// lines added or removed above them, and changed lines become their replacement.
+fn map_ranges(ranges: &[RangeInclusive<usize>], hunks: &[DiffHunk]) -> Vec<RangeInclusive<usize>> {
+    let mut mapped = Vec::new();
+    for range in ranges {
+        let (mut start, end) = (*range.start(), *range.end());
+        let mut shift: isize = 0;
+        for h in hunks {
+            if start > end {
+                break;
+            }
+            // Unchanged lines before the hunk
+            let first_changed = if h.old_count == 0 {
+                h.old_start + 1
+            } else {
+                h.old_start
+            };
+            if start < first_changed {
+                let last = end.min(first_changed - 1);
+                mapped.push(start.saturating_add_signed(shift)..=last.saturating_add_signed(shift));
+                start = last + 1;
+            }
+            // Changed lines of the hunk
+            let old_end = h.old_start + h.old_count;
+            if start <= end && start < old_end {
+                if h.new_count > 0 {
+                    mapped.push(h.new_start..=h.new_start + h.new_count - 1);
+                }
+                start = old_end;
+            }
+            shift += h.new_count as isize - h.old_count as isize;
+        }
+        if start <= end {
+            mapped.push(start.saturating_add_signed(shift)..=end.saturating_add_signed(shift));
+        }
+    }
+    mapped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn added(diff: &str) -> AddedLines {
+        added_ranges(parse_diff(Path::new("/repo"), diff))
+    }
+
+    #[test]
+    fn hunks_with_and_without_count() {
+        let diff = "\
+diff --git a/m.c b/m.c
+--- a/m.c
++++ b/m.c
+@@ -1,0 +2,3 @@ int main() {
+@@ -5 +8 @@ int main() {
+@@ -9,2 +11,0 @@ int main() {
+";
+        assert_eq!(added(diff)[Path::new("/repo/m.c")], vec![2..=4, 8..=8]);
+    }
+
+    #[test]
+    fn renamed_file_is_keyed_by_new_path() {
+        let diff = "\
+diff --git a/old.c b/src/new.c
+similarity index 90%
+rename from old.c
+rename to src/new.c
+--- a/old.c
++++ b/src/new.c
+@@ -3,0 +4 @@ void f() {
+";
+        let added = added(diff);
+        assert_eq!(added[Path::new("/repo/src/new.c")], vec![4..=4]);
+        assert!(!added.contains_key(Path::new("/repo/old.c")));
+    }
+
+    #[test]
+    fn deleted_file_has_no_added_lines() {
+        let diff = "\
+diff --git a/gone.c b/gone.c
+deleted file mode 100644
+--- a/gone.c
++++ /dev/null
+@@ -1,3 +0,0 @@
+diff --git a/new.c b/new.c
+new file mode 100644
+--- /dev/null
++++ b/new.c
+@@ -0,0 +1,2 @@
+";
+        let added = added(diff);
+        assert_eq!(added.len(), 1);
+        assert_eq!(added[Path::new("/repo/new.c")], vec![1..=2]);
+    }
+
+    #[test]
+    fn paths_with_spaces_and_quotes() {
+        let diff = "\
+--- a/a b.c\t
++++ b/a b.c\t
+@@ -1 +1,2 @@
+--- \"a/q\\\"x.c\"
++++ \"b/q\\\"x.c\"
+@@ -1 +2 @@
+";
+        let added = added(diff);
+        assert_eq!(added[Path::new("/repo/a b.c")], vec![1..=2]);
+        assert_eq!(added[Path::new("/repo/q\"x.c")], vec![2..=2]);
+    }
+
+    #[test]
+    fn lines_follow_the_changes_above_them() {
+        let hunks = [
+            // Two lines inserted after line 1
+            DiffHunk {
+                old_start: 1,
+                old_count: 0,
+                new_start: 2,
+                new_count: 2,
+            },
+            // Line 5 replaced by three lines
+            DiffHunk {
+                old_start: 5,
+                old_count: 1,
+                new_start: 7,
+                new_count: 3,
+            },
+            // Line 8 removed
+            DiffHunk {
+                old_start: 8,
+                old_count: 1,
+                new_start: 11,
+                new_count: 0,
+            },
+        ];
+        assert_eq!(map_ranges(&[1..=1], &hunks), vec![1..=1]);
+        assert_eq!(map_ranges(&[3..=3], &hunks), vec![5..=5]);
+        assert_eq!(map_ranges(&[4..=6], &hunks), vec![6..=6, 7..=9, 10..=10]);
+        assert_eq!(
+            map_ranges(&[8..=8], &hunks),
+            Vec::<RangeInclusive<usize>>::new()
+        );
+        assert_eq!(map_ranges(&[9..=9], &hunks), vec![12..=12]);
+    }
+}
@@ -0,0 +1,280 @@
+// Journal of the edits written by `on`, `off` and `delete`, so that `flop undo`
+// can revert them. Each operation is a JSON file in `.flop/journal/` at the root
+// of the git repository of the working directory, or in the working directory
+// outside of one, whatever the paths edited, so that `undo` finds it. Numbered
+// from a counter that only goes up. Edits are recorded as whole lines before and
+// after, and an operation is only reverted while those lines are still as flop
+// left them.
+
+use anyhow::{bail, Context, Result};
+use encoding_rs::Encoding;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::git;
+use crate::line_index::LineIndex;
+use crate::source::{self, SourceFile};
+
+// Older operations are dropped beyond this many
+const MAX_OPERATIONS: usize = 50;
+
+// Last operation id handed out, in `.flop/`
+const LAST_ID_FILE: &str = "last-id";
+
+// Unchanged lines kept around each hunk, so it can be found again if lines were
+// added or removed elsewhere in the file
+const CONTEXT_LINES: usize = 2;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Operation {
+    pub id: u64,
+    pub time: u64, // Seconds since the Unix epoch
+    pub command: String,
+    pub files: Vec<FileChange>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileChange {
+    pub path: PathBuf, // Absolute path
+    pub encoding: String,
+    pub hunks: Vec<Hunk>,
+}
+
+// Range an edit replaced in the original text, and the range of its replacement
+// in the edited text
+pub type EditRange = (Range<usize>, Range<usize>);
+
+// Whole lines of a file before and after an edit, with some context
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Hunk {
+    pub line: usize, // First line of the hunk in the edited file
+    pub before: String,
+    pub after: String,
+}
+
+// Group the edits that turned `original` into `edited`, in order, into hunks of
+// whole lines
+pub fn hunks(original: &str, edited: &str, ranges: &[EditRange]) -> Vec<Hunk> {
+    let original_lines = LineIndex::new(original);
+    let edited_lines = LineIndex::new(edited);
+
+    // First and last line of each hunk, in the original and the edited file
+    let mut spans: Vec<(Range<usize>, Range<usize>)> = Vec::new();
+    for (old, new) in ranges {
+        let old = original_lines
+            .line_number(old.start)
+            .saturating_sub(CONTEXT_LINES)
+            .max(1)
+            ..(original_lines.line_number(old.end) + CONTEXT_LINES)
+                .min(original_lines.line_count());
+        let new = edited_lines
+            .line_number(new.start)
+            .saturating_sub(CONTEXT_LINES)
+            .max(1)
+            ..(edited_lines.line_number(new.end) + CONTEXT_LINES).min(edited_lines.line_count());
+        match spans.last_mut() {
+            // Edits close to each other make a single hunk
+            Some((last_old, last_new)) if old.start <= last_old.end => {
+                last_old.end = last_old.end.max(old.end);
+                last_new.end = last_new.end.max(new.end);
+            }
+            _ => spans.push((old, new)),
+        }
+    }
+
+    spans
+        .into_iter()
+        .map(|(old, new)| Hunk {
+            line: new.start,
+            before: original[original_lines.line_range(old.start).start
+                ..original_lines.line_range(old.end).end]
+                .to_string(),
+            after: edited
+                [edited_lines.line_range(new.start).start..edited_lines.line_range(new.end).end]
+                .to_string(),
+        })
+        .collect()
+}
+
+// Record an operation in the journal of the working directory `cwd`, returning
+// its id
+pub fn record(cwd: &Path, command: &str, files: Vec<FileChange>) -> Result<u64> {
+    let dir = journal_dir(cwd);
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+    // The journal is local state, never something to commit
+    let gitignore = dir.parent().unwrap_or(&dir).join(".gitignore");
+    if !gitignore.exists() {
+        fs::write(&gitignore, "*\n")
+            .with_context(|| format!("Failed to write file: {}", gitignore.display()))?;
+    }
+
+    // Ids are never reused, even once operations are undone or dropped, so that
+    // an id printed earlier cannot come to mean another operation
+    let mut operations = load(&dir)?;
+    let counter = dir.parent().unwrap_or(&dir).join(LAST_ID_FILE);
+    let last_id = fs::read_to_string(&counter)
+        .ok()
+        .and_then(|text| text.trim().parse::<u64>().ok())
+        .unwrap_or(0);
+    let id = last_id.max(operations.last().map_or(0, |op| op.id)) + 1;
+    fs::write(&counter, format!("{}\n", id))
+        .with_context(|| format!("Failed to write file: {}", counter.display()))?;
+    let operation = Operation {
+        id,
+        time: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+        command: command.to_string(),
+        files,
+    };
+    let file = dir.join(format!("{}.json", id));
+    fs::write(&file, serde_json::to_string_pretty(&operation)?)
+        .with_context(|| format!("Failed to write file: {}", file.display()))?;
+
+    operations.push(operation);
+    let excess = operations.len().saturating_sub(MAX_OPERATIONS);
+    for old in &operations[..excess] {
+        let _ = fs::remove_file(dir.join(format!("{}.json", old.id)));
+    }
+
+    Ok(id)
+}
+
+// Recorded operations, oldest first
+pub fn list(cwd: &Path) -> Result<Vec<Operation>> {
+    load(&journal_dir(cwd))
+}
+
+// Revert an operation (the most recent one by default) and remove it from the
+// journal. Refuses if any of its lines changed since.
+pub fn undo(cwd: &Path, id: Option<u64>) -> Result<Operation> {
+    let dir = journal_dir(cwd);
+    let operations = load(&dir)?;
+    let operation = match id {
+        Some(id) => operations.into_iter().find(|op| op.id == id),
+        None => operations.into_iter().last(),
+    };
+    let Some(operation) = operation else {
+        match id {
+            Some(id) => bail!("No operation {} in the journal", id),
+            None => bail!("Nothing to undo"),
+        }
+    };
+
+    let mut writes = Vec::new();
+    let mut conflicts = Vec::new();
+    for file in &operation.files {
+        let encoding = Encoding::for_label(file.encoding.as_bytes())
+            .with_context(|| format!("Unknown encoding: {}", file.encoding))?;
+        let source = match SourceFile::read_as(&file.path, encoding) {
+            Ok(source) => source,
+            Err(_) => {
+                conflicts.push(format!("{}: cannot be read", file.path.display()));
+                continue;
+            }
+        };
+        let content = &source.text;
+        let lines = LineIndex::new(content);
+
+        let mut replacements = Vec::new();
+        for hunk in &file.hunks {
+            match locate(content, &lines, hunk) {
+                Some(start) => replacements.push((start, hunk)),
+                None => conflicts.push(format!(
+                    "{}:{}: changed since operation {}",
+                    file.path.display(),
+                    hunk.line,
+                    operation.id
+                )),
+            }
+        }
+        replacements.sort_by_key(|&(start, _)| start);
+
+        let mut reverted = String::with_capacity(content.len());
+        let mut pos = 0;
+        for (start, hunk) in replacements {
+            if start < pos {
+                conflicts.push(format!(
+                    "{}:{}: overlapping edits",
+                    file.path.display(),
+                    hunk.line
+                ));
+                continue;
+            }
+            reverted.push_str(&content[pos..start]);
+            reverted.push_str(&hunk.before);
+            pos = start + hunk.after.len();
+        }
+        reverted.push_str(&content[pos..]);
+        writes.push((file.path.clone(), source.encode(&file.path, &reverted)?));
+    }
+
+    if !conflicts.is_empty() {
+        bail!(
+            "Cannot undo operation {}, the files changed since:\n  {}",
+            operation.id,
+            conflicts.join("\n  ")
+        );
+    }
+    source::write_all(writes)?;
+
+    let file = dir.join(format!("{}.json", operation.id));
+    fs::remove_file(&file).with_context(|| format!("Failed to remove file: {}", file.display()))?;
+    Ok(operation)
+}
+
+// Offset of the lines of a hunk in the current content: at the recorded line,
+// or anywhere else if they only appear once, as when lines were added above
+fn locate(content: &str, lines: &LineIndex, hunk: &Hunk) -> Option<usize> {
+    let matches_at = |line: usize| {
+        let start = lines.line_range(line).start;
+        let end = start + hunk.after.len();
+        (content[start..].starts_with(&hunk.after)
+            && lines.line_range(lines.line_number(end)).end == end)
+            .then_some(start)
+    };
+
+    if hunk.line <= lines.line_count() {
+        if let Some(start) = matches_at(hunk.line) {
+            return Some(start);
+        }
+    }
+    let mut found = (1..=lines.line_count()).filter_map(matches_at);
+    match (found.next(), found.next()) {
+        (Some(start), None) => Some(start),
+        _ => None,
+    }
+}
+
+// `.flop/journal` at the root of the repository containing `cwd`, or in `cwd`
+// outside of a repository
+fn journal_dir(cwd: &Path) -> PathBuf {
+    git::repository_root(cwd)
+        .unwrap_or_else(|| cwd.to_path_buf())
+        .join(".flop")
+        .join("journal")
+}
+
+fn load(dir: &Path) -> Result<Vec<Operation>> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(Vec::new());
+    };
+
+    let mut operations = Vec::new();
+    for entry in entries {
+        let file = entry?.path();
+        if file.extension().is_some_and(|ext| ext == "json") {
+            let text = fs::read_to_string(&file)
+                .with_context(|| format!("Failed to read file: {}", file.display()))?;
+            let operation: Operation = serde_json::from_str(&text)
+                .with_context(|| format!("Invalid journal entry: {}", file.display()))?;
+            operations.push(operation);
+        }
+    }
+    operations.sort_by_key(|op| op.id);
+    Ok(operations)
+}
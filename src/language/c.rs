@@ -0,0 +1,39 @@
+use super::{Language, OutputPattern, StatementEnd};
+use crate::lexer::Dialect;
+
+pub struct C;
+
+// Standard I/O functions, shared with C++ where they may be called as `std::printf`
+pub const STDIO_PATTERN: OutputPattern = OutputPattern {
+    head: r"\b(std::)?(printf|fprintf|sprintf|snprintf|printf_debug|dprintf|puts|fputs|fputc|putchar|fputchar|write|perror)\s*\(",
+    end: StatementEnd::Call,
+    requires_keyword: true,
+};
+
+impl Language for C {
+    fn name(&self) -> &'static str {
+        "c"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["c"]
+    }
+
+    fn dialect(&self) -> Dialect {
+        Dialect::C
+    }
+
+    fn output_patterns(&self) -> &'static [OutputPattern] {
+        &[STDIO_PATTERN]
+    }
+
+    #[cfg(feature = "tree-sitter")]
+    fn grammar(&self) -> tree_sitter::Language {
+        tree_sitter_c::LANGUAGE.into()
+    }
+
+    #[cfg(feature = "tree-sitter")]
+    fn function_kinds(&self) -> &'static [&'static str] {
+        &["function_definition"]
+    }
+}
@@ -0,0 +1,42 @@
+use super::{c, Language, OutputPattern, StatementEnd};
+use crate::lexer::Dialect;
+
+pub struct Cpp;
+
+impl Language for Cpp {
+    fn name(&self) -> &'static str {
+        "cpp"
+    }
+
+    // `.h` headers are shared by C and C++ code, and C++ covers both
+    fn extensions(&self) -> &'static [&'static str] {
+        &["cpp", "hpp", "cc", "cxx", "h"]
+    }
+
+    fn dialect(&self) -> Dialect {
+        Dialect::Cpp
+    }
+
+    fn output_patterns(&self) -> &'static [OutputPattern] {
+        &[
+            // C++ code still uses the C standard I/O functions
+            c::STDIO_PATTERN,
+            // Streams
+            OutputPattern {
+                head: r"(std::cout|std::cerr|std::clog)\s*<<",
+                end: StatementEnd::Terminator,
+                requires_keyword: true,
+            },
+        ]
+    }
+
+    #[cfg(feature = "tree-sitter")]
+    fn grammar(&self) -> tree_sitter::Language {
+        tree_sitter_cpp::LANGUAGE.into()
+    }
+
+    #[cfg(feature = "tree-sitter")]
+    fn function_kinds(&self) -> &'static [&'static str] {
+        &["function_definition"]
+    }
+}
@@ -0,0 +1,41 @@
+use super::{Language, OutputPattern, StatementEnd};
+use crate::lexer::Dialect;
+
+pub struct Go;
+
+impl Language for Go {
+    fn name(&self) -> &'static str {
+        "go"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["go"]
+    }
+
+    fn dialect(&self) -> Dialect {
+        Dialect::Go
+    }
+
+    fn output_patterns(&self) -> &'static [OutputPattern] {
+        &[OutputPattern {
+            head: r"\b(fmt\.(Println|Printf|Print|Fprintln|Fprintf|Fprint)|log\.(Println|Printf|Print|Fatal|Fatalf|Fatalln|Panic|Panicf|Panicln))\s*\(",
+            end: StatementEnd::Call,
+            requires_keyword: true,
+        }]
+    }
+
+    // Go doesn't require semicolons
+    fn terminator(&self) -> Option<char> {
+        None
+    }
+
+    #[cfg(feature = "tree-sitter")]
+    fn grammar(&self) -> tree_sitter::Language {
+        tree_sitter_go::LANGUAGE.into()
+    }
+
+    #[cfg(feature = "tree-sitter")]
+    fn function_kinds(&self) -> &'static [&'static str] {
+        &["function_declaration", "method_declaration"]
+    }
+}
@@ -0,0 +1,36 @@
+use super::{Language, OutputPattern, StatementEnd};
+use crate::lexer::Dialect;
+
+pub struct Java;
+
+impl Language for Java {
+    fn name(&self) -> &'static str {
+        "java"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["java"]
+    }
+
+    fn dialect(&self) -> Dialect {
+        Dialect::Java
+    }
+
+    fn output_patterns(&self) -> &'static [OutputPattern] {
+        &[OutputPattern {
+            head: r"\bSystem\.(out|err)\.(println|printf|print)\s*\(",
+            end: StatementEnd::Call,
+            requires_keyword: true,
+        }]
+    }
+
+    #[cfg(feature = "tree-sitter")]
+    fn grammar(&self) -> tree_sitter::Language {
+        tree_sitter_java::LANGUAGE.into()
+    }
+
+    #[cfg(feature = "tree-sitter")]
+    fn function_kinds(&self) -> &'static [&'static str] {
+        &["method_declaration", "constructor_declaration"]
+    }
+}
@@ -0,0 +1,82 @@
+use std::path::Path;
+
+use crate::lexer::Dialect;
+
+pub mod c;
+pub mod cpp;
+pub mod go;
+pub mod java;
+pub mod rust;
+
+// Written right after the comment marker by `flop on`, so that `flop off` only
+// restores the statements flop disabled itself
+pub const DISABLED_TAG: &str = "flop:";
+
+// How the end of a statement is found once its head has matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatementEnd {
+    // A call whose arguments start at the delimiter ending the head
+    Call,
+    // Everything up to the next top-level terminator, like C++ streams
+    Terminator,
+}
+
+pub struct OutputPattern {
+    // Regex matching the statement up to (and including) its opening delimiter
+    pub head: &'static str,
+    pub end: StatementEnd,
+    // Whether --debug requires the keyword inside the statement
+    pub requires_keyword: bool,
+}
+
+pub trait Language: Sync {
+    // Short identifier, e.g. "c" or "rust"
+    fn name(&self) -> &'static str;
+
+    fn extensions(&self) -> &'static [&'static str];
+
+    // Literal and comment rules used by the lexer
+    fn dialect(&self) -> Dialect;
+
+    fn output_patterns(&self) -> &'static [OutputPattern];
+
+    fn line_comment(&self) -> &'static str {
+        "//"
+    }
+
+    // Opening and closing markers of a block comment
+    fn block_comment(&self) -> (&'static str, &'static str) {
+        ("/*", "*/")
+    }
+
+    // Character ending a statement, or None if it is optional (as in Go)
+    fn terminator(&self) -> Option<char> {
+        Some(';')
+    }
+
+    #[cfg(feature = "tree-sitter")]
+    fn grammar(&self) -> tree_sitter::Language;
+
+    // Syntax node kinds of the function definitions reported as enclosing scope
+    #[cfg(feature = "tree-sitter")]
+    fn function_kinds(&self) -> &'static [&'static str];
+}
+
+static LANGUAGES: &[&dyn Language] = &[&c::C, &cpp::Cpp, &rust::Rust, &java::Java, &go::Go];
+
+pub fn all() -> &'static [&'static dyn Language] {
+    LANGUAGES
+}
+
+pub fn for_extension(ext: &str) -> Option<&'static dyn Language> {
+    LANGUAGES
+        .iter()
+        .copied()
+        .find(|lang| lang.extensions().contains(&ext))
+}
+
+pub fn for_path(path: &Path) -> Option<&'static dyn Language> {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .and_then(for_extension)
+}
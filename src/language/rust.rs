@@ -0,0 +1,45 @@
+use super::{Language, OutputPattern, StatementEnd};
+use crate::lexer::Dialect;
+
+pub struct Rust;
+
+impl Language for Rust {
+    fn name(&self) -> &'static str {
+        "rust"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["rs"]
+    }
+
+    fn dialect(&self) -> Dialect {
+        Dialect::Rust
+    }
+
+    fn output_patterns(&self) -> &'static [OutputPattern] {
+        &[
+            // dbg! is always debug output
+            OutputPattern {
+                head: r"\bdbg!\s*[(\[{]",
+                end: StatementEnd::Call,
+                requires_keyword: false,
+            },
+            // Print macros
+            OutputPattern {
+                head: r"\b(println!|eprintln!|print!|eprint!)\s*[(\[{]",
+                end: StatementEnd::Call,
+                requires_keyword: true,
+            },
+        ]
+    }
+
+    #[cfg(feature = "tree-sitter")]
+    fn grammar(&self) -> tree_sitter::Language {
+        tree_sitter_rust::LANGUAGE.into()
+    }
+
+    #[cfg(feature = "tree-sitter")]
+    fn function_kinds(&self) -> &'static [&'static str] {
+        &["function_item"]
+    }
+}
@@ -0,0 +1,700 @@
+// Small per-language lexer used to find where output statements really end.
+// It only knows enough about each language to skip comments and literals,
+// so that parens, brackets and semicolons inside strings are not miscounted.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    C,
+    Cpp,
+    Rust,
+    Java,
+    Go,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    LineComment,
+    BlockComment,
+    Literal,
+}
+
+// A comment or literal, as a half-open byte range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub kind: TokenKind,
+}
+
+// Collect every comment and literal in the file, in order
+pub fn non_code_spans(content: &str, dialect: Dialect) -> Vec<Span> {
+    let bytes = content.as_bytes();
+    let mut spans = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if let Some((kind, end)) = skip_token(bytes, i, dialect) {
+            spans.push(Span {
+                start: i,
+                end,
+                kind,
+            });
+            i = end;
+        } else {
+            i += 1;
+        }
+    }
+
+    spans
+}
+
+// Find the comment or literal containing `offset`, if any
+pub fn span_at(spans: &[Span], offset: usize) -> Option<&Span> {
+    let idx = spans.partition_point(|s| s.end <= offset);
+    spans.get(idx).filter(|s| s.start <= offset)
+}
+
+// Given the offset of an opening `(`, `[` or `{`, return the offset just past
+// its matching closer. When `commented` is set the statement lives inside `//`
+// comments, so the comment marker at the start of each following line is skipped.
+pub fn find_closing(
+    content: &str,
+    open: usize,
+    dialect: Dialect,
+    commented: bool,
+) -> Option<usize> {
+    let bytes = content.as_bytes();
+    let mut stack = vec![closer_for(*bytes.get(open)?)?];
+    let mut i = open + 1;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\n' if commented => {
+                i = skip_comment_prefix(bytes, i + 1);
+                continue;
+            }
+            b'(' | b'[' | b'{' => stack.push(closer_for(bytes[i]).unwrap()),
+            b')' | b']' | b'}' => {
+                if stack.pop() != Some(bytes[i]) {
+                    return None;
+                }
+                if stack.is_empty() {
+                    return Some(i + 1);
+                }
+            }
+            _ => {
+                if let Some((_, end)) = skip_token(bytes, i, dialect) {
+                    i = end;
+                    continue;
+                }
+            }
+        }
+        i += 1;
+    }
+
+    None
+}
+
+// Return the offset just past the `terminator` ending the statement that starts
+// at `start`. Used for statements that are not a single call, like C++ streams.
+pub fn find_statement_end(
+    content: &str,
+    start: usize,
+    dialect: Dialect,
+    terminator: char,
+    commented: bool,
+) -> Option<usize> {
+    let bytes = content.as_bytes();
+    let mut depth = 0usize;
+    let mut i = start;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\n' if commented => {
+                i = skip_comment_prefix(bytes, i + 1);
+                continue;
+            }
+            b if depth == 0 && char::from(b) == terminator => return Some(i + 1),
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => {
+                // Closing a block we never opened: the statement has no terminator
+                if depth == 0 {
+                    return None;
+                }
+                depth -= 1;
+            }
+            _ => {
+                if let Some((_, end)) = skip_token(bytes, i, dialect) {
+                    i = end;
+                    continue;
+                }
+            }
+        }
+        i += 1;
+    }
+
+    None
+}
+
+// Where a statement sits in the code around it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    // Among the other statements of a block, so it can simply be removed
+    Block,
+    // The whole body of an unbraced `if`, loop or `else`, or the statement after a
+    // label: without it, the next statement would take its place
+    Body,
+}
+
+// Position of the statement starting at `start`, judged from the code before it.
+// None when it is not a statement of its own but part of a larger one, like
+// `int n = printf(...)`, `return printf(...)` or `defer fmt.Println(...)`.
+pub fn statement_position(
+    content: &str,
+    spans: &[Span],
+    start: usize,
+    dialect: Dialect,
+) -> Option<Position> {
+    let bytes = content.as_bytes();
+    let c_like = matches!(dialect, Dialect::C | Dialect::Cpp | Dialect::Java);
+
+    // Last code byte before the statement, skipping blanks and comments
+    let mut i = start;
+    let mut newline = false;
+    let prev = loop {
+        let Some(j) = i.checked_sub(1) else {
+            return Some(Position::Block);
+        };
+        if let Some(span) = span_at(spans, j) {
+            if span.kind == TokenKind::Literal {
+                break j;
+            }
+            i = span.start;
+            continue;
+        }
+        match bytes[j] {
+            b'\n' => newline = true,
+            b if b.is_ascii_whitespace() => {}
+            _ => break j,
+        }
+        i = j;
+    };
+
+    let line_start = content[..prev].rfind('\n').map_or(0, |p| p + 1);
+    if newline {
+        // Preprocessor directives end with their line
+        if matches!(dialect, Dialect::C | Dialect::Cpp)
+            && content[line_start..].trim_start().starts_with('#')
+        {
+            return Some(Position::Block);
+        }
+        // Go ends a statement at a line break after an operand or `++`/`--`
+        let b = bytes[prev];
+        if dialect == Dialect::Go
+            && (is_ident_byte(b)
+                || matches!(b, b')' | b']' | b'}' | b'"' | b'\'' | b'`')
+                || (matches!(b, b'+' | b'-') && prev > 0 && bytes[prev - 1] == b))
+        {
+            return Some(Position::Block);
+        }
+    }
+
+    match bytes[prev] {
+        b';' | b'{' | b'}' => Some(Position::Block),
+        // `case x:`, `default:` or a label, but not `a ? b : c`. Only C and C++
+        // labels must be followed by a statement.
+        b':' if dialect != Dialect::Rust => {
+            let label = label_before(content, line_start, prev);
+            let is_label = is_case(label)
+                || (label.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                    && label.bytes().all(is_ident_byte));
+            match dialect {
+                _ if !is_label => None,
+                Dialect::C | Dialect::Cpp => Some(Position::Body),
+                _ => Some(Position::Block),
+            }
+        }
+        b')' if c_like => {
+            let open = matching_open(bytes, spans, prev)?;
+            matches!(
+                word_before(content, open),
+                "if" | "for" | "while" | "switch" | "constexpr"
+            )
+            .then_some(Position::Body)
+        }
+        // A `case x ->` rule, but not a lambda
+        b'>' if dialect == Dialect::Java && prev > 0 && bytes[prev - 1] == b'-' => {
+            is_case(label_before(content, line_start, prev - 1)).then_some(Position::Body)
+        }
+        b if c_like && is_ident_byte(b) => {
+            matches!(word_before(content, prev + 1), "else" | "do").then_some(Position::Body)
+        }
+        _ => None,
+    }
+}
+
+// Code from the start of the line or statement to `end`, like `case 1` before `:`
+fn label_before(content: &str, line_start: usize, end: usize) -> &str {
+    content[line_start..end]
+        .rsplit([';', '{', '}'])
+        .next()
+        .unwrap_or_default()
+        .trim()
+}
+
+fn is_case(label: &str) -> bool {
+    label == "default"
+        || label
+            .strip_prefix("case")
+            .is_some_and(|rest| !rest.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_'))
+}
+
+// Offset of the `(` matching the `)` at `close`
+fn matching_open(bytes: &[u8], spans: &[Span], close: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut i = close + 1;
+    while i > 0 {
+        i -= 1;
+        if let Some(span) = span_at(spans, i) {
+            i = span.start;
+            continue;
+        }
+        match bytes[i] {
+            b')' => depth += 1,
+            b'(' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// The identifier just before `end`, if any
+fn word_before(content: &str, end: usize) -> &str {
+    let before = content[..end].trim_end();
+    let len = before
+        .bytes()
+        .rev()
+        .take_while(|&b| is_ident_byte(b))
+        .count();
+    &before[before.len() - len..]
+}
+
+fn closer_for(open: u8) -> Option<u8> {
+    match open {
+        b'(' => Some(b')'),
+        b'[' => Some(b']'),
+        b'{' => Some(b'}'),
+        _ => None,
+    }
+}
+
+fn skip_comment_prefix(bytes: &[u8], mut i: usize) -> usize {
+    while i < bytes.len() && (bytes[i] == b' ' || bytes[i] == b'\t') {
+        i += 1;
+    }
+    if bytes[i..].starts_with(b"//") {
+        i += 2;
+    }
+    i
+}
+
+fn is_ident_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+// If a comment or literal starts at `i`, return its kind and the offset just past it
+fn skip_token(bytes: &[u8], i: usize, dialect: Dialect) -> Option<(TokenKind, usize)> {
+    let rest = &bytes[i..];
+    match bytes[i] {
+        b'/' if rest.starts_with(b"//") => {
+            let end = memchr(b'\n', bytes, i).unwrap_or(bytes.len());
+            Some((TokenKind::LineComment, end))
+        }
+        b'/' if rest.starts_with(b"/*") => Some((
+            TokenKind::BlockComment,
+            skip_block_comment(bytes, i, dialect == Dialect::Rust),
+        )),
+        b'"' if dialect == Dialect::Java && rest.starts_with(b"\"\"\"") => {
+            Some((TokenKind::Literal, skip_text_block(bytes, i)))
+        }
+        b'"' => Some((
+            TokenKind::Literal,
+            skip_quoted(bytes, i, b'"', dialect == Dialect::Rust),
+        )),
+        b'\'' => skip_char_literal(bytes, i, dialect).map(|end| (TokenKind::Literal, end)),
+        b'`' if dialect == Dialect::Go => {
+            let end = memchr(b'`', bytes, i + 1).map_or(bytes.len(), |p| p + 1);
+            Some((TokenKind::Literal, end))
+        }
+        b'b' | b'c' | b'r' if dialect == Dialect::Rust => {
+            skip_rust_raw_string(bytes, i).map(|end| (TokenKind::Literal, end))
+        }
+        b'u' | b'U' | b'L' | b'R' if dialect == Dialect::Cpp => {
+            skip_cpp_raw_string(bytes, i).map(|end| (TokenKind::Literal, end))
+        }
+        _ => None,
+    }
+}
+
+fn memchr(needle: u8, bytes: &[u8], from: usize) -> Option<usize> {
+    bytes[from..]
+        .iter()
+        .position(|&b| b == needle)
+        .map(|p| p + from)
+}
+
+fn skip_block_comment(bytes: &[u8], start: usize, nested: bool) -> usize {
+    let mut depth = 0usize;
+    let mut i = start;
+
+    while i + 1 < bytes.len() {
+        if bytes[i] == b'/' && bytes[i + 1] == b'*' {
+            if depth == 0 || nested {
+                depth += 1;
+            }
+            i += 2;
+        } else if bytes[i] == b'*' && bytes[i + 1] == b'/' {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return i;
+            }
+        } else {
+            i += 1;
+        }
+    }
+
+    bytes.len()
+}
+
+// Skip a string or char literal delimited by `quote`, honoring backslash escapes.
+// Unless `multiline` is set, an unescaped newline ends an unterminated literal.
+fn skip_quoted(bytes: &[u8], start: usize, quote: u8, multiline: bool) -> usize {
+    let mut i = start + 1;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            // An escaped CRLF line continuation is one escape, not two
+            b'\\' if bytes[i + 1..].starts_with(b"\r\n") => i += 3,
+            b'\\' => i += 2,
+            b'\n' if !multiline => return i,
+            b if b == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+
+    bytes.len()
+}
+
+fn skip_text_block(bytes: &[u8], start: usize) -> usize {
+    let mut i = start + 3;
+
+    while i < bytes.len() {
+        if bytes[i] == b'\\' {
+            i += 2;
+        } else if bytes[i..].starts_with(b"\"\"\"") {
+            return i + 3;
+        } else {
+            i += 1;
+        }
+    }
+
+    bytes.len()
+}
+
+fn skip_char_literal(bytes: &[u8], start: usize, dialect: Dialect) -> Option<usize> {
+    match dialect {
+        // 'a', '\n', '\u{1F600}' are chars; 'a and 'label are lifetimes
+        Dialect::Rust => {
+            if bytes.get(start + 1) == Some(&b'\\') {
+                return Some(skip_quoted(bytes, start, b'\'', false));
+            }
+            let ch_len = utf8_len(*bytes.get(start + 1)?);
+            (bytes.get(start + 1 + ch_len) == Some(&b'\'')).then_some(start + ch_len + 2)
+        }
+        // C++14 digit separators, as in 1'000'000
+        Dialect::Cpp if start > 0 && bytes[start - 1].is_ascii_digit() => None,
+        _ => Some(skip_quoted(bytes, start, b'\'', false)),
+    }
+}
+
+fn utf8_len(lead: u8) -> usize {
+    match lead {
+        0xF0.. => 4,
+        0xE0.. => 3,
+        0xC0.. => 2,
+        _ => 1,
+    }
+}
+
+// r"...", r#"..."#, br"...", cr"..."
+fn skip_rust_raw_string(bytes: &[u8], start: usize) -> Option<usize> {
+    if start > 0 && is_ident_byte(bytes[start - 1]) {
+        return None;
+    }
+
+    let mut i = start;
+    if matches!(bytes[i], b'b' | b'c') {
+        i += 1;
+    }
+    if bytes.get(i) != Some(&b'r') {
+        return None;
+    }
+    i += 1;
+
+    let hashes = bytes[i..].iter().take_while(|&&b| b == b'#').count();
+    i += hashes;
+    if bytes.get(i) != Some(&b'"') {
+        return None;
+    }
+    i += 1;
+
+    let mut terminator = vec![b'"'];
+    terminator.resize(hashes + 1, b'#');
+    Some(find_subslice(bytes, i, &terminator).map_or(bytes.len(), |p| p + terminator.len()))
+}
+
+// R"delim(...)delim", optionally prefixed by u8, u, U or L
+fn skip_cpp_raw_string(bytes: &[u8], start: usize) -> Option<usize> {
+    if start > 0 && is_ident_byte(bytes[start - 1]) {
+        return None;
+    }
+
+    let rest = &bytes[start..];
+    let prefix_len = [&b"u8R\""[..], b"uR\"", b"UR\"", b"LR\"", b"R\""]
+        .iter()
+        .find(|p| rest.starts_with(p))?
+        .len();
+
+    let delim_start = start + prefix_len;
+    let open = memchr(b'(', bytes, delim_start)?;
+    let delim = &bytes[delim_start..open];
+    if delim.len() > 16 || delim.iter().any(|b| b.is_ascii_whitespace() || *b == b')') {
+        return None;
+    }
+
+    let mut terminator = vec![b')'];
+    terminator.extend_from_slice(delim);
+    terminator.push(b'"');
+    Some(find_subslice(bytes, open + 1, &terminator).map_or(bytes.len(), |p| p + terminator.len()))
+}
+
+fn find_subslice(bytes: &[u8], from: usize, needle: &[u8]) -> Option<usize> {
+    bytes[from..]
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|p| p + from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Text of the call opening at the first `open` in `source`
+    fn call(source: &str, open: char, dialect: Dialect) -> &str {
+        let start = source.find(open).unwrap();
+        let end = find_closing(source, start, dialect, false).unwrap();
+        &source[start..end]
+    }
+
+    // Text of the statement starting `source`
+    fn statement(source: &str, dialect: Dialect) -> &str {
+        &source[..find_statement_end(source, 0, dialect, ';', false).unwrap()]
+    }
+
+    #[test]
+    fn nested_parens() {
+        assert_eq!(
+            call("printf(\"%d\", f(g(1), (2))); x();", '(', Dialect::C),
+            "(\"%d\", f(g(1), (2)))"
+        );
+        assert_eq!(find_closing("f(a, (b)", 1, Dialect::C, false), None);
+        assert_eq!(find_closing("f(a]", 1, Dialect::C, false), None);
+    }
+
+    #[test]
+    fn char_literals() {
+        assert_eq!(call("putchar(')'); x();", '(', Dialect::C), "(')')");
+        assert_eq!(call("putchar('\\''); x();", '(', Dialect::C), "('\\'')");
+        assert_eq!(
+            call("print!(\"{}\", ')'); x();", '(', Dialect::Rust),
+            "(\"{}\", ')')"
+        );
+        assert_eq!(
+            call("print!(\"{}\", '\\u{29}'); x();", '(', Dialect::Rust),
+            "(\"{}\", '\\u{29}')"
+        );
+        // Lifetimes and labels are not char literals
+        assert_eq!(
+            call("f::<'a>(x, 'outer); g(')')", '(', Dialect::Rust),
+            "(x, 'outer)"
+        );
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            call("printf(\"\\\")(\\\\\"); x();", '(', Dialect::C),
+            "(\"\\\")(\\\\\")"
+        );
+        // A line continuation inside a string, with either line terminator
+        for newline in ["\n", "\r\n"] {
+            let source = format!("printf(\"a \\{}b)\"); x();", newline);
+            assert_eq!(
+                call(&source, '(', Dialect::C),
+                format!("(\"a \\{}b)\")", newline)
+            );
+        }
+    }
+
+    #[test]
+    fn rust_raw_strings() {
+        assert_eq!(
+            call(
+                "println!(r#\"a \"quoted\" ) str\"#); x();",
+                '(',
+                Dialect::Rust
+            ),
+            "(r#\"a \"quoted\" ) str\"#)"
+        );
+        assert_eq!(
+            call("println!(\"{}\", br\"\\\"); x();", '(', Dialect::Rust),
+            "(\"{}\", br\"\\\")"
+        );
+        // An identifier ending in `r` is not a raw string prefix
+        assert_eq!(
+            call("println!(\"{}\", ar); x(\")\");", '(', Dialect::Rust),
+            "(\"{}\", ar)"
+        );
+    }
+
+    #[test]
+    fn go_raw_strings() {
+        assert_eq!(
+            call("fmt.Println(`a ) \\ \"\nb`, `)`); x()", '(', Dialect::Go),
+            "(`a ) \\ \"\nb`, `)`)"
+        );
+    }
+
+    #[test]
+    fn cpp_raw_strings() {
+        assert_eq!(
+            statement("std::cout << R\"d(a ); \")d\" << x; y;", Dialect::Cpp),
+            "std::cout << R\"d(a ); \")d\" << x;"
+        );
+        assert_eq!(
+            statement("std::cout << u8R\"(;)\"; y;", Dialect::Cpp),
+            "std::cout << u8R\"(;)\";"
+        );
+        // Not raw strings in C
+        assert_eq!(
+            statement("printf(R\"(\"); y;", Dialect::C),
+            "printf(R\"(\");"
+        );
+    }
+
+    #[test]
+    fn brace_and_bracket_macros() {
+        assert_eq!(
+            call("println!{\"{}}\", x} y();", '{', Dialect::Rust),
+            "{\"{}}\", x}"
+        );
+        assert_eq!(
+            call("dbg![a[0], (b), \"]\"]; y();", '[', Dialect::Rust),
+            "[a[0], (b), \"]\"]"
+        );
+    }
+
+    #[test]
+    fn digit_separators() {
+        assert_eq!(
+            statement("std::cout << 1'000'000 << ';' << x; y;", Dialect::Cpp),
+            "std::cout << 1'000'000 << ';' << x;"
+        );
+    }
+
+    #[test]
+    fn commented_statements() {
+        let source = "//flop: printf(\"a\",\n    //flop:        \")\", b);\nx();";
+        let open = source.find('(').unwrap();
+        let end = find_closing(source, open, Dialect::C, true).unwrap();
+        assert!(source[..end].ends_with("b)"));
+    }
+
+    #[test]
+    fn statement_positions() {
+        // Position of the statement at the `P` marker
+        fn position(source: &str, dialect: Dialect) -> Option<Position> {
+            let spans = non_code_spans(source, dialect);
+            statement_position(source, &spans, source.find('P').unwrap(), dialect)
+        }
+
+        let c = [
+            ("P();", Some(Position::Block)),
+            ("x(); /* c */ P();", Some(Position::Block)),
+            ("#ifdef DEBUG\nP();", Some(Position::Block)),
+            ("if (f(x)) P();", Some(Position::Body)),
+            ("} else\n  P();", Some(Position::Body)),
+            ("case 'a': P();", Some(Position::Body)),
+            ("int n = P();", None),
+            ("return P();", None),
+            ("(void)P();", None),
+            ("f(x) P();", None),
+            ("x ? y : P();", None),
+        ];
+        for (source, expected) in c {
+            assert_eq!(position(source, Dialect::C), expected, "{}", source);
+        }
+
+        assert_eq!(
+            position("case 1 -> P();", Dialect::Java),
+            Some(Position::Body)
+        );
+        assert_eq!(position("r = () -> P();", Dialect::Java), None);
+        assert_eq!(
+            position("case 1:\n  P()", Dialect::Go),
+            Some(Position::Block)
+        );
+        assert_eq!(position("x++\n  P()", Dialect::Go), Some(Position::Block));
+        assert_eq!(position("x :=\n  P()", Dialect::Go), None);
+        assert_eq!(position("defer P()", Dialect::Go), None);
+        assert_eq!(position("let y = P();", Dialect::Rust), None);
+    }
+
+    #[test]
+    fn comments_and_literals() {
+        fn kinds(source: &str, dialect: Dialect) -> Vec<(&str, TokenKind)> {
+            non_code_spans(source, dialect)
+                .iter()
+                .map(|s| (&source[s.start..s.end], s.kind))
+                .collect()
+        }
+        assert_eq!(
+            kinds("a /* x /* y */ b */ c // d\ne", Dialect::Rust),
+            vec![
+                ("/* x /* y */ b */", TokenKind::BlockComment),
+                ("// d", TokenKind::LineComment),
+            ]
+        );
+        assert_eq!(
+            kinds("a /* x /* y */ b */", Dialect::C),
+            vec![("/* x /* y */", TokenKind::BlockComment)]
+        );
+        assert_eq!(
+            kinds("s = \"\"\"\n  \"quoted\" \"\"\"; c = 'x';", Dialect::Java),
+            vec![
+                ("\"\"\"\n  \"quoted\" \"\"\"", TokenKind::Literal),
+                ("'x'", TokenKind::Literal),
+            ]
+        );
+        assert_eq!(
+            span_at(&non_code_spans("a \"b\" c", Dialect::C), 3).map(|s| s.start),
+            Some(2)
+        );
+    }
+}
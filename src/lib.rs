@@ -1,6 +1,19 @@
+pub mod baseline;
 pub mod cli;
+pub mod config;
+pub mod diff;
 pub mod editor;
 pub mod finder;
+pub mod git;
+pub mod journal;
+pub mod language;
+pub mod lexer;
+pub mod line_index;
+pub mod output;
 pub mod processor;
+pub mod scope;
+pub mod source;
+#[cfg(feature = "tree-sitter")]
+pub mod syntax;
 pub mod types;
 pub mod ui;
@@ -0,0 +1,45 @@
+// Byte offsets of every line start in a file, so offsets can be mapped to
+// line numbers without rescanning the content for each statement
+pub struct LineIndex {
+    starts: Vec<usize>,
+    // End of each line's text, before its `\n` or `\r\n`
+    ends: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(content: &str) -> Self {
+        let bytes = content.as_bytes();
+        let mut starts = vec![0];
+        let mut ends = Vec::new();
+        for (i, _) in bytes.iter().enumerate().filter(|&(_, &b)| b == b'\n') {
+            ends.push(if i > 0 && bytes[i - 1] == b'\r' {
+                i - 1
+            } else {
+                i
+            });
+            starts.push(i + 1);
+        }
+        ends.push(content.len());
+        Self { starts, ends }
+    }
+
+    // 1-based number of the line containing `offset`
+    pub fn line_number(&self, offset: usize) -> usize {
+        self.starts.partition_point(|&start| start <= offset)
+    }
+
+    // Offset of the start of the line containing `offset`
+    pub fn line_start(&self, offset: usize) -> usize {
+        self.starts[self.line_number(offset) - 1]
+    }
+
+    // Byte range of a 1-based line, without its line terminator
+    pub fn line_range(&self, number: usize) -> std::ops::Range<usize> {
+        self.starts[number - 1]..self.ends[number - 1]
+    }
+
+    // Number of lines, counting the (possibly empty) text after the last newline
+    pub fn line_count(&self) -> usize {
+        self.starts.len()
+    }
+}
@@ -3,47 +3,138 @@ use clap::Parser;
 use std::path::PathBuf;
 
 use flop_cli::cli::{Cli, Commands};
-use flop_cli::processor::{process_path, process_path_delete};
+use flop_cli::config::Config;
+use flop_cli::processor::{
+    process_baseline, process_check, process_list, process_path, process_path_delete,
+    process_status, process_undo, Mode,
+};
+use flop_cli::ui::init_color;
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    init_color(cli.color);
 
     match cli.command {
         Commands::Off {
             path,
+            any_comment,
             debug,
             yes,
             preview,
+            patch,
+            mut scan,
         } => {
+            scan.any_comment = any_comment;
             let target_path = path.unwrap_or_else(|| PathBuf::from("."));
-            let all = !debug;
+            let yes = yes || Config::for_path(&target_path)?.yes == Some(true);
+            let all = !debug && scan.keywords.keyword.is_empty();
             let interactive = !yes;
             let skip_confirm = yes;
-            process_path(&target_path, true, skip_confirm, all, interactive, preview)?;
+            process_path(
+                &target_path,
+                true,
+                skip_confirm,
+                all,
+                interactive,
+                &Mode::new(preview, patch),
+                &scan,
+            )?;
         }
         Commands::On {
             path,
             debug,
             yes,
             preview,
+            patch,
+            scan,
         } => {
             let target_path = path.unwrap_or_else(|| PathBuf::from("."));
-            let all = !debug;
+            let yes = yes || Config::for_path(&target_path)?.yes == Some(true);
+            let all = !debug && scan.keywords.keyword.is_empty();
             let interactive = !yes;
             let skip_confirm = yes;
-            process_path(&target_path, false, skip_confirm, all, interactive, preview)?;
+            process_path(
+                &target_path,
+                false,
+                skip_confirm,
+                all,
+                interactive,
+                &Mode::new(preview, patch),
+                &scan,
+            )?;
         }
         Commands::Delete {
             path,
+            any_comment,
             debug,
             yes,
             preview,
+            patch,
+            mut scan,
         } => {
+            scan.any_comment = any_comment;
             let target_path = path.unwrap_or_else(|| PathBuf::from("."));
-            let all = !debug;
+            let yes = yes || Config::for_path(&target_path)?.yes == Some(true);
+            let all = !debug && scan.keywords.keyword.is_empty();
             let interactive = !yes;
             let skip_confirm = yes;
-            process_path_delete(&target_path, skip_confirm, all, interactive, preview)?;
+            process_path_delete(
+                &target_path,
+                skip_confirm,
+                all,
+                interactive,
+                &Mode::new(preview, patch),
+                &scan,
+            )?;
+        }
+        Commands::List {
+            path,
+            any_comment,
+            debug,
+            format,
+            mut scan,
+        } => {
+            scan.any_comment = any_comment;
+            let target_path = path.unwrap_or_else(|| PathBuf::from("."));
+            let all = !debug && scan.keywords.keyword.is_empty();
+            process_list(&target_path, all, format, &scan)?;
+        }
+        Commands::Status {
+            path,
+            any_comment,
+            debug,
+            mut scan,
+        } => {
+            scan.any_comment = any_comment;
+            let target_path = path.unwrap_or_else(|| PathBuf::from("."));
+            let all = !debug && scan.keywords.keyword.is_empty();
+            process_status(&target_path, all, &scan)?;
+        }
+        Commands::Check {
+            path,
+            debug,
+            format,
+            baseline,
+            scan,
+        } => {
+            let target_path = path.unwrap_or_else(|| PathBuf::from("."));
+            let all = !debug && scan.keywords.keyword.is_empty();
+            if process_check(&target_path, all, format, baseline.as_deref(), &scan)? {
+                std::process::exit(1);
+            }
+        }
+        Commands::Baseline {
+            path,
+            debug,
+            output,
+            scan,
+        } => {
+            let target_path = path.unwrap_or_else(|| PathBuf::from("."));
+            let all = !debug && scan.keywords.keyword.is_empty();
+            process_baseline(&target_path, all, output.as_deref(), &scan)?;
+        }
+        Commands::Undo { id, list } => {
+            process_undo(id, list)?;
         }
     }
 
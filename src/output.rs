@@ -0,0 +1,292 @@
+// Listings of the statements found for tools: JSON for scripts, editor plugins
+// and dashboards, grep-like lines for editor jump lists, SARIF and checkstyle
+// for code scanning and CI dashboards, and compiler-style diagnostics for
+// `flop check`
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::cli::Format;
+use crate::git;
+use crate::source::SourceFile;
+use crate::types::Match;
+use crate::ui::display_matches;
+
+// A statement as serialized by `--format json` and `--format jsonl`
+#[derive(Serialize)]
+struct Statement<'a> {
+    file: &'a Path,
+    line: usize,
+    end_line: usize,
+    column: usize,
+    end_column: usize, // Exclusive, like `end_byte`
+    language: &'a str,
+    function: Option<&'a str>,
+    parent_kind: Option<&'a str>,
+    commented: bool,
+    text: String,
+    keywords: &'a [String],
+}
+
+impl<'a> From<&'a Match> for Statement<'a> {
+    fn from(m: &'a Match) -> Self {
+        Statement {
+            file: display_path(&m.file_path),
+            line: m.line_number,
+            end_line: m.end_line_number,
+            column: m.start_column,
+            end_column: m.end_column,
+            language: m.language,
+            function: m.function_name.as_deref(),
+            parent_kind: m.parent_kind,
+            commented: m.commented,
+            text: m.multiline_content.join("\n"),
+            keywords: &m.keywords,
+        }
+    }
+}
+
+pub fn print_matches(matches: &[Match], format: Format) -> Result<()> {
+    match write_matches(&mut io::stdout().lock(), matches, format) {
+        // Output piped to `head` or a closed fzf
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+fn write_matches(out: &mut impl Write, matches: &[Match], format: Format) -> io::Result<()> {
+    match format {
+        Format::Text => display_matches(matches),
+        Format::Json => {
+            let statements: Vec<Statement> = matches.iter().map(Statement::from).collect();
+            serde_json::to_writer_pretty(&mut *out, &statements)?;
+            writeln!(out)?;
+        }
+        Format::Jsonl => {
+            for m in matches {
+                serde_json::to_writer(&mut *out, &Statement::from(m))?;
+                writeln!(out)?;
+            }
+        }
+        Format::Grep => {
+            for m in matches {
+                writeln!(
+                    out,
+                    "{}:{}:{}:{}",
+                    display_path(&m.file_path).display(),
+                    m.line_number,
+                    m.start_column,
+                    m.line_content
+                )?;
+            }
+        }
+        Format::Sarif => {
+            serde_json::to_writer_pretty(&mut *out, &sarif(matches))?;
+            writeln!(out)?;
+        }
+        Format::Checkstyle => write!(out, "{}", checkstyle(matches))?,
+    }
+    Ok(())
+}
+
+// SARIF 2.1.0, as read by GitHub code scanning and most review tools. Files are
+// given relative to the root of the repository (or the current directory) as
+// %SRCROOT%, and columns are counted in UTF-16 code units as SARIF expects.
+fn sarif(matches: &[Match]) -> serde_json::Value {
+    let root = git::repository_root(Path::new("."))
+        .or_else(|| fs::canonicalize(".").ok())
+        .unwrap_or_default();
+    let mut texts: HashMap<&Path, Option<String>> = HashMap::new();
+
+    let mut rules: BTreeMap<String, &str> = BTreeMap::new();
+    let results: Vec<_> = matches
+        .iter()
+        .map(|m| {
+            let rule = rule_id(m);
+            rules.insert(rule.clone(), m.language);
+            let text = texts.entry(&m.file_path).or_insert_with(|| {
+                SourceFile::read_as(&m.file_path, m.encoding)
+                    .ok()
+                    .map(|source| source.text)
+            });
+            let start_column = utf16_column(text.as_deref(), m.start_byte, m.start_column);
+            let end_column = utf16_column(text.as_deref(), m.end_byte, m.end_column);
+            json!({
+                "ruleId": rule,
+                "level": "warning",
+                "message": { "text": message(m) },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": artifact_location(&root, &m.file_path),
+                        "region": {
+                            "startLine": m.line_number,
+                            "startColumn": start_column,
+                            "endLine": m.end_line_number,
+                            "endColumn": end_column,
+                        },
+                    },
+                }],
+            })
+        })
+        .collect();
+
+    let rules: Vec<_> = rules
+        .into_iter()
+        .map(|(id, language)| {
+            let name = id.split_once('/').map_or(id.as_str(), |(_, name)| name);
+            json!({
+                "id": id,
+                "shortDescription": { "text": format!("Output statement `{}` in {} code", name, language) },
+                "defaultConfiguration": { "level": "warning" },
+            })
+        })
+        .collect();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "flop",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "rules": rules,
+                },
+            },
+            "originalUriBaseIds": {
+                "%SRCROOT%": { "uri": file_uri(&root) + "/" },
+            },
+            "columnKind": "utf16CodeUnits",
+            "results": results,
+        }],
+    })
+}
+
+// Relative to %SRCROOT%, or an absolute `file://` URI for files outside of it
+fn artifact_location(root: &Path, path: &Path) -> serde_json::Value {
+    let absolute = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    match absolute.strip_prefix(root) {
+        Ok(relative) => json!({
+            "uri": uri_escape(&relative.to_string_lossy().replace('\\', "/")),
+            "uriBaseId": "%SRCROOT%",
+        }),
+        Err(_) => json!({ "uri": file_uri(&absolute) }),
+    }
+}
+
+fn file_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    // Windows paths start with a drive letter
+    let slash = if path.starts_with('/') { "" } else { "/" };
+    format!("file://{}{}", slash, uri_escape(&path))
+}
+
+// Percent-encode everything but unreserved characters and separators
+fn uri_escape(path: &str) -> String {
+    let mut escaped = String::new();
+    for &byte in path.as_bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/:".contains(&byte) {
+            escaped.push(byte as char);
+        } else {
+            escaped += &format!("%{:02X}", byte);
+        }
+    }
+    escaped
+}
+
+// Column of `offset` in UTF-16 code units, from its column in bytes. Falls back
+// to the byte column if the file cannot be read again.
+fn utf16_column(text: Option<&str>, offset: usize, byte_column: usize) -> usize {
+    let line_start = offset + 1 - byte_column;
+    text.and_then(|text| text.get(line_start..offset))
+        .map_or(byte_column, |prefix| prefix.encode_utf16().count() + 1)
+}
+
+// Checkstyle XML, as read by Jenkins warnings and other CI dashboards
+fn checkstyle(matches: &[Match]) -> String {
+    let mut files: BTreeMap<&Path, Vec<&Match>> = BTreeMap::new();
+    for m in matches {
+        files.entry(display_path(&m.file_path)).or_default().push(m);
+    }
+
+    let mut xml =
+        String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<checkstyle version=\"4.3\">\n");
+    for (file, file_matches) in files {
+        xml += &format!(
+            "  <file name=\"{}\">\n",
+            xml_escape(&file.to_string_lossy())
+        );
+        for m in file_matches {
+            xml += &format!(
+                "    <error line=\"{}\" column=\"{}\" severity=\"warning\" message=\"{}\" source=\"flop.{}\"/>\n",
+                m.line_number,
+                m.start_column,
+                xml_escape(&message(m)),
+                xml_escape(&rule_id(m))
+            );
+        }
+        xml += "  </file>\n";
+    }
+    xml += "</checkstyle>\n";
+    xml
+}
+
+// `<language>/<kind>`, where the kind is named after the function, macro or
+// stream the statement calls: `c/printf`, `rust/dbg-macro`, `cpp/cerr`,
+// `go/fmt-println`, `java/system-out-println`
+fn rule_id(m: &Match) -> String {
+    let text = m.multiline_content.first().map_or("", |line| line.trim());
+    let callee = text
+        .split(['(', '[', '{', '<', ' ', '\t'])
+        .next()
+        .unwrap_or_default();
+    let callee = callee.strip_prefix("std::").unwrap_or(callee);
+    let kind = match callee.strip_suffix('!') {
+        Some(name) => format!("{}-macro", name),
+        None => callee.replace("::", "-").replace('.', "-"),
+    };
+    format!("{}/{}", m.language, kind.to_lowercase())
+}
+
+fn message(m: &Match) -> String {
+    // Statements with a keyword are most likely leftover debugging
+    let kind = if m.keywords.is_empty() {
+        "output"
+    } else {
+        "debug output"
+    };
+    format!("{} statement: {}", kind, m.line_content)
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// One `file:line:col: message` line per statement, the format compilers use so
+// that editors and CI annotate the right place
+pub fn print_diagnostics(matches: &[Match]) {
+    for m in matches {
+        println!(
+            "{}:{}:{}: {}",
+            display_path(&m.file_path).display(),
+            m.line_number,
+            m.start_column,
+            message(m)
+        );
+    }
+}
+
+// `./src/main.c` (from walking the current directory) as `src/main.c`
+pub fn display_path(path: &Path) -> &Path {
+    path.strip_prefix(".").unwrap_or(path)
+}
@@ -1,10 +1,38 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
+use std::collections::BTreeMap;
 use std::io::{self, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::editor::{apply_changes, delete_changes};
-use crate::finder::find_debug_printfs;
-use crate::ui::{display_matches, select_statements_interactive};
+use crate::baseline;
+use crate::cli::{Format, ScanOptions};
+use crate::diff::{print_diff, write_patch};
+use crate::editor::{
+    apply_changes, delete_changes, plan_changes, plan_delete, statement_count, FileEdit,
+};
+use crate::finder::{find_debug_printfs, find_matches, report_unreadable};
+use crate::journal::{self, FileChange};
+use crate::output::{display_path, print_diagnostics, print_matches};
+use crate::ui::{can_select_interactively, display_matches, paint, select_statements_interactive};
+
+// What is done with the selected statements
+pub enum Mode {
+    Apply,
+    // Show the diff of the changes without making them
+    Preview,
+    // Write the changes as a patch instead of making them
+    Patch(PathBuf),
+}
+
+impl Mode {
+    pub fn new(preview: bool, patch: Option<PathBuf>) -> Self {
+        match patch {
+            Some(file) => Mode::Patch(file),
+            None if preview => Mode::Preview,
+            None => Mode::Apply,
+        }
+    }
+}
 
 pub fn process_path(
     path: &Path,
@@ -12,9 +40,12 @@ pub fn process_path(
     skip_confirm: bool,
     detect_all: bool,
     interactive: bool,
-    dry_run: bool,
+    mode: &Mode,
+    scan: &ScanOptions,
 ) -> Result<()> {
-    let matches = find_debug_printfs(path, uncomment, detect_all)?;
+    let dry_run = !matches!(mode, Mode::Apply);
+    let interactive = interactive && terminal_available();
+    let matches = find_debug_printfs(path, uncomment, detect_all, scan)?;
 
     if matches.is_empty() {
         println!("No matching debug statements found.");
@@ -58,6 +89,8 @@ pub fn process_path(
     }
 
     if dry_run {
+        let file_edits = plan_changes(&selected_matches, uncomment)?;
+        show_changes(&file_edits, mode)?;
         println!(
             "\n[DRY RUN] Would {} {} statement(s).",
             if uncomment {
@@ -65,14 +98,12 @@ pub fn process_path(
             } else {
                 "disable (comment out)"
             },
-            selected_matches.len()
+            statement_count(&file_edits)
         );
     } else {
-        apply_changes(&selected_matches, uncomment)?;
-        println!(
-            "\nSuccessfully processed {} statement(s).",
-            selected_matches.len()
-        );
+        let (statements, changes) = apply_changes(&selected_matches, uncomment)?;
+        println!("\nSuccessfully processed {} statement(s).", statements);
+        record(if uncomment { "off" } else { "on" }, changes);
     }
 
     Ok(())
@@ -83,11 +114,15 @@ pub fn process_path_delete(
     skip_confirm: bool,
     detect_all: bool,
     interactive: bool,
-    dry_run: bool,
+    mode: &Mode,
+    scan: &ScanOptions,
 ) -> Result<()> {
+    let dry_run = !matches!(mode, Mode::Apply);
+    let interactive = interactive && terminal_available();
     // Find both commented and uncommented debug statements
-    let uncommented_matches = find_debug_printfs(path, false, detect_all)?;
-    let commented_matches = find_debug_printfs(path, true, detect_all)?;
+    let (uncommented_matches, unreadable) = find_matches(path, false, detect_all, scan)?;
+    report_unreadable(&unreadable, scan)?;
+    let (commented_matches, _) = find_matches(path, true, detect_all, scan)?;
 
     // Combine both lists
     let mut all_matches = uncommented_matches;
@@ -133,17 +168,254 @@ pub fn process_path_delete(
     }
 
     if dry_run {
+        let file_edits = plan_delete(&selected_matches)?;
+        show_changes(&file_edits, mode)?;
         println!(
             "\n[DRY RUN] Would delete {} statement(s).",
-            selected_matches.len()
+            statement_count(&file_edits)
         );
     } else {
-        delete_changes(&selected_matches)?;
-        println!(
-            "\nSuccessfully deleted {} statement(s).",
-            selected_matches.len()
-        );
+        let (statements, changes) = delete_changes(&selected_matches)?;
+        println!("\nSuccessfully deleted {} statement(s).", statements);
+        record("delete", changes);
+    }
+
+    Ok(())
+}
+
+// The interactive selection needs a terminal; without one, list the statements
+// and ask for confirmation on stdin instead of hanging
+fn terminal_available() -> bool {
+    if can_select_interactively() {
+        return true;
+    }
+    eprintln!("Not a terminal: listing the statements instead of the interactive selection.");
+    false
+}
+
+// Print the diff of the changes, or write it to the patch file
+fn show_changes(file_edits: &[FileEdit], mode: &Mode) -> Result<()> {
+    match mode {
+        Mode::Patch(file) => {
+            write_patch(file_edits, file)?;
+            println!("\nWrote patch to {}", file.display());
+        }
+        _ => {
+            println!();
+            print_diff(file_edits);
+        }
+    }
+    Ok(())
+}
+
+pub fn process_list(
+    path: &Path,
+    detect_all: bool,
+    format: Format,
+    scan: &ScanOptions,
+) -> Result<()> {
+    let (mut matches, unreadable) = find_matches(path, false, detect_all, scan)?;
+    report_unreadable(&unreadable, scan)?;
+    let (commented_matches, _) = find_matches(path, true, detect_all, scan)?;
+    matches.extend(commented_matches);
+    matches.sort_by(|a, b| (&a.file_path, a.start_byte).cmp(&(&b.file_path, b.start_byte)));
+
+    if matches.is_empty() && format == Format::Text {
+        println!("No matching debug statements found.");
+        return Ok(());
+    }
+    print_matches(&matches, format)
+}
+
+// Report the active statements without changing anything. Returns whether any
+// was found, so that the caller can fail.
+pub fn process_check(
+    path: &Path,
+    detect_all: bool,
+    format: Format,
+    baseline: Option<&Path>,
+    scan: &ScanOptions,
+) -> Result<bool> {
+    // A mistyped path must not pass the check
+    if !path.exists() {
+        bail!("Path not found: {}", path.display());
+    }
+    let mut matches = find_debug_printfs(path, false, detect_all, scan)?;
+
+    // The default baseline is used when there is one
+    let baseline = baseline.map(Path::to_path_buf).or_else(|| {
+        let default_file = baseline::default_file(path);
+        default_file.exists().then_some(default_file)
+    });
+    if let Some(file) = baseline {
+        let total = matches.len();
+        matches = baseline::new_statements(&file, matches)?;
+        if total > matches.len() {
+            eprintln!(
+                "{} statement(s) in the baseline {} ignored.",
+                total - matches.len(),
+                file.display()
+            );
+        }
+    }
+
+    match format {
+        Format::Text => print_diagnostics(&matches),
+        _ => print_matches(&matches, format)?,
+    }
+    if !matches.is_empty() {
+        eprintln!("Found {} output statement(s).", matches.len());
+    }
+    Ok(!matches.is_empty())
+}
+
+pub fn process_baseline(
+    path: &Path,
+    detect_all: bool,
+    file: Option<&Path>,
+    scan: &ScanOptions,
+) -> Result<()> {
+    let matches = find_debug_printfs(path, false, detect_all, scan)?;
+    let file = file.map_or_else(|| baseline::default_file(path), Path::to_path_buf);
+    baseline::write(&file, &matches)?;
+    println!(
+        "Recorded {} statement(s) in {}.",
+        matches.len(),
+        file.display()
+    );
+    Ok(())
+}
+
+// Overview of the active and disabled statements per file, with totals per
+// language and per function
+pub fn process_status(path: &Path, detect_all: bool, scan: &ScanOptions) -> Result<()> {
+    let (active, unreadable) = find_matches(path, false, detect_all, scan)?;
+    report_unreadable(&unreadable, scan)?;
+    let (disabled, _) = find_matches(path, true, detect_all, scan)?;
+    if active.is_empty() && disabled.is_empty() {
+        println!("No matching debug statements found.");
+        return Ok(());
+    }
+
+    // Active and disabled count of each file, language and function
+    let mut files: BTreeMap<&Path, [usize; 2]> = BTreeMap::new();
+    let mut languages: BTreeMap<&str, [usize; 2]> = BTreeMap::new();
+    let mut functions: BTreeMap<(&Path, &str), [usize; 2]> = BTreeMap::new();
+    for (state, m) in active
+        .iter()
+        .map(|m| (0, m))
+        .chain(disabled.iter().map(|m| (1, m)))
+    {
+        let file = display_path(&m.file_path);
+        let function = m.function_name.as_deref().unwrap_or("(outside functions)");
+        files.entry(file).or_default()[state] += 1;
+        languages.entry(m.language).or_default()[state] += 1;
+        functions.entry((file, function)).or_default()[state] += 1;
+    }
+
+    let totals = [active.len(), disabled.len()];
+    match write_status(
+        &mut io::stdout().lock(),
+        &files,
+        &languages,
+        &functions,
+        totals,
+    ) {
+        // Output piped to `head`
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+fn write_status(
+    out: &mut impl Write,
+    files: &BTreeMap<&Path, [usize; 2]>,
+    languages: &BTreeMap<&str, [usize; 2]>,
+    functions: &BTreeMap<(&Path, &str), [usize; 2]>,
+    totals: [usize; 2],
+) -> io::Result<()> {
+    for (file, counts) in files {
+        writeln!(
+            out,
+            "{}: {}",
+            paint("35", &file.display().to_string()),
+            counts_text(counts)
+        )?;
     }
+    writeln!(out, "\nTotal: {}", counts_text(&totals))?;
 
+    writeln!(out, "\nBy language:")?;
+    for (language, counts) in languages {
+        writeln!(out, "  {}: {}", language, counts_text(counts))?;
+    }
+
+    writeln!(out, "\nBy function:")?;
+    for ((file, function), counts) in functions {
+        writeln!(
+            out,
+            "  {}: {}: {}",
+            file.display(),
+            function,
+            counts_text(counts)
+        )?;
+    }
+    Ok(())
+}
+
+fn counts_text([active, disabled]: &[usize; 2]) -> String {
+    format!("{} active, {} disabled", active, disabled)
+}
+
+// Record the changes for `flop undo`, in the journal `undo` reads: the one of the
+// working directory, not of the paths edited. The files are already written by
+// then, so failing to record them is only a warning.
+fn record(command: &str, changes: Vec<FileChange>) {
+    match journal::record(Path::new("."), command, changes) {
+        Ok(id) => println!("Revert with `flop undo {}`.", id),
+        Err(err) => eprintln!(
+            "Warning: could not record the operation for undo: {:#}",
+            err
+        ),
+    }
+}
+
+pub fn process_undo(id: Option<u64>, list: bool) -> Result<()> {
+    let path = Path::new(".");
+    if list {
+        let operations = journal::list(path)?;
+        if operations.is_empty() {
+            println!("No operations recorded.");
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        for op in operations.iter().rev() {
+            println!(
+                "{:>4}  {:<7} {:>3} file(s)  {}",
+                op.id,
+                op.command,
+                op.files.len(),
+                age(now.saturating_sub(op.time))
+            );
+        }
+        return Ok(());
+    }
+
+    let operation = journal::undo(path, id)?;
+    println!(
+        "Reverted operation {} ({}) in {} file(s).",
+        operation.id,
+        operation.command,
+        operation.files.len()
+    );
     Ok(())
 }
+
+fn age(seconds: u64) -> String {
+    match seconds {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{} min ago", seconds / 60),
+        3600..86400 => format!("{} h ago", seconds / 3600),
+        _ => format!("{} days ago", seconds / 86400),
+    }
+}
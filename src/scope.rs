@@ -0,0 +1,292 @@
+// Enclosing functions for the lexer backend, which has no syntax tree: function
+// bodies are found from their braces and the header before them. Good enough to
+// group and fingerprint statements by function, not to understand the code.
+
+use regex::Regex;
+use std::sync::LazyLock;
+
+use crate::lexer::{span_at, Dialect, Span};
+
+// Words followed by parentheses that start a block without being a function
+const CONTROL_KEYWORDS: &[&str] = &[
+    "if",
+    "for",
+    "while",
+    "switch",
+    "catch",
+    "try",
+    "synchronized",
+    "constexpr",
+    "return",
+    "sizeof",
+    "decltype",
+];
+
+static RUST_FN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\bfn\s+(?:r#)?(\w+)").expect("valid regex"));
+static GO_FUNC: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\bfunc\s*(?:\([^)]*\)\s*)?(\w+)").expect("valid regex"));
+
+// Body of a function, as the byte range between its braces
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scope {
+    pub start: usize,
+    pub end: usize,
+    pub name: String,
+}
+
+// Every function body in the file, in order
+pub fn function_scopes(content: &str, spans: &[Span], dialect: Dialect) -> Vec<Scope> {
+    let bytes = content.as_bytes();
+    let mut open = Vec::new();
+    let mut scopes = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if let Some(span) = span_at(spans, i) {
+            i = span.end;
+            continue;
+        }
+        match bytes[i] {
+            b'{' => open.push(i),
+            b'}' => {
+                if let Some(start) = open.pop() {
+                    if let Some(name) = function_name(&header(content, spans, start), dialect) {
+                        scopes.push(Scope {
+                            start,
+                            end: i + 1,
+                            name,
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    scopes.sort_by_key(|s| s.start);
+
+    // Rust and Go name their functions with a keyword, and may nest them. C, C++
+    // and Java have no nested functions, so a nested "function" is a macro like
+    // `list_for_each(...) {` and the outermost one is right.
+    if !matches!(dialect, Dialect::Rust | Dialect::Go) {
+        let mut outermost: Vec<Scope> = Vec::new();
+        for scope in scopes {
+            if outermost.last().is_none_or(|last| scope.start >= last.end) {
+                outermost.push(scope);
+            }
+        }
+        scopes = outermost;
+    }
+    scopes
+}
+
+// Name of the innermost function containing `offset`
+pub fn function_at(scopes: &[Scope], offset: usize) -> Option<String> {
+    scopes
+        .iter()
+        .rfind(|s| s.start < offset && offset < s.end)
+        .map(|s| s.name.clone())
+}
+
+// Code before the `{` at `brace`, back to the previous statement or block, with
+// comments and literals blanked out. Parenthesized groups are skipped whole, so
+// that `;` in `[u8; 4]` or `for (;;)` does not end the header.
+fn header(content: &str, spans: &[Span], brace: usize) -> String {
+    let bytes = content.as_bytes();
+    let mut depth = 0usize;
+    let mut start = brace;
+    while start > 0 {
+        let i = start - 1;
+        if let Some(span) = span_at(spans, i) {
+            start = span.start;
+            continue;
+        }
+        match bytes[i] {
+            b')' | b']' => depth += 1,
+            // An unclosed group: the block is an argument, like a lambda
+            b'(' | b'[' if depth == 0 => break,
+            b'(' | b'[' => depth -= 1,
+            b';' | b'{' | b'}' if depth == 0 => break,
+            _ => {}
+        }
+        start = i;
+    }
+
+    let mut text = content.as_bytes()[start..brace].to_vec();
+    for span in spans.iter().filter(|s| s.end > start && s.start < brace) {
+        for byte in &mut text[span.start.max(start) - start..span.end.min(brace) - start] {
+            *byte = b' ';
+        }
+    }
+    String::from_utf8_lossy(&text).into_owned()
+}
+
+fn function_name(header: &str, dialect: Dialect) -> Option<String> {
+    let keyword_fn = match dialect {
+        Dialect::Rust => &RUST_FN,
+        Dialect::Go => &GO_FUNC,
+        Dialect::C | Dialect::Cpp | Dialect::Java => return declarator_name(header),
+    };
+    keyword_fn.captures(header).map(|caps| caps[1].to_string())
+}
+
+// Name before the first parameter list of a C, C++ or Java function header, like
+// `bar` in `static int bar(int x) {` or `Foo::bar` in `void Foo::bar() const {`
+fn declarator_name(header: &str) -> Option<String> {
+    let bytes = header.as_bytes();
+    let mut depth = 0usize;
+    for (i, &byte) in bytes.iter().enumerate() {
+        match byte {
+            // The name of a function returning a function pointer is inside
+            // `(*name(params))`
+            b'(' if depth == 0 && header[i + 1..].trim_start().starts_with('*') => {}
+            b'(' if depth == 0 => {
+                depth = 1;
+                let before = header[..i].trim_end();
+                let name_start = before
+                    .rfind(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | ':' | '~')))
+                    .map_or(0, |p| p + 1);
+                let name = &before[name_start..];
+                let rest = before[..name_start].trim_end();
+                let previous_word = rest
+                    .rsplit(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .next()
+                    .unwrap_or_default();
+                let is_name = name.starts_with(|c: char| c.is_alphabetic() || matches!(c, '_' | '~'))
+                    && !CONTROL_KEYWORDS.contains(&name)
+                    // Annotations, calls on objects and anonymous classes
+                    && !rest.ends_with(['@', '.', '='])
+                    && !rest.ends_with("->")
+                    && previous_word != "new";
+                if is_name && header[i..].contains(')') {
+                    return Some(name.to_string());
+                }
+            }
+            b'(' => depth += 1,
+            b')' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::non_code_spans;
+
+    // Function around each `HERE` marker in the source
+    fn functions(source: &str, dialect: Dialect) -> Vec<Option<String>> {
+        let spans = non_code_spans(source, dialect);
+        let scopes = function_scopes(source, &spans, dialect);
+        source
+            .match_indices("HERE")
+            .map(|(offset, _)| function_at(&scopes, offset))
+            .collect()
+    }
+
+    fn named(names: &[&str]) -> Vec<Option<String>> {
+        names.iter().map(|n| Some(n.to_string())).collect()
+    }
+
+    #[test]
+    fn c_functions() {
+        let source = "\
+#include <stdio.h>
+static const char *s = \"{ HERE\";
+struct point { int x; };
+static int add(int a, int b) {
+    if (a > b) { HERE; }
+    for (;;) { HERE; }
+    list_for_each(pos, head) { HERE; }
+    return a + b;
+}
+int (*get(void))(int) { HERE; }
+";
+        let mut expected = vec![None];
+        expected.extend(named(&["add", "add", "add", "get"]));
+        assert_eq!(functions(source, Dialect::C), expected);
+    }
+
+    #[test]
+    fn commented_statements_are_in_their_function() {
+        let source = "void f() {\n    //flop: printf(\"HERE\");\n    /* } */\n    // HERE\n}\n";
+        assert_eq!(functions(source, Dialect::C), named(&["f", "f"]));
+    }
+
+    #[test]
+    fn cpp_methods_and_lambdas() {
+        let source = "\
+namespace app {
+class Server {
+    void start() const { HERE; }
+};
+Server::Server(int port) : port_(port) { HERE; }
+void Server::run() {
+    auto log = [&](int x) { HERE; };
+    std::for_each(v.begin(), v.end(), [](int x) { HERE; });
+}
+Server::~Server() { HERE; }
+}
+";
+        assert_eq!(
+            functions(source, Dialect::Cpp),
+            named(&[
+                "start",
+                "Server::Server",
+                "Server::run",
+                "Server::run",
+                "Server::~Server"
+            ])
+        );
+    }
+
+    #[test]
+    fn java_methods() {
+        let source = "\
+class A {
+    @SuppressWarnings(\"unchecked\")
+    public <T> void run(List<T> items) throws IOException {
+        new Thread() { public void start() { HERE; } };
+        items.forEach(item -> { HERE; });
+        synchronized (this) { HERE; }
+    }
+    static { HERE; }
+}
+";
+        let mut expected = named(&["run", "run", "run"]);
+        expected.push(None);
+        assert_eq!(functions(source, Dialect::Java), expected);
+    }
+
+    #[test]
+    fn rust_functions() {
+        let source = "\
+/// Calls fn other()
+fn main() {
+    if check(x) { HERE; }
+    let f = |x| { HERE; };
+    fn inner(buf: [u8; 4]) -> Result<(), E> { HERE; }
+}
+impl Foo {
+    pub fn r#type(&self) where T: Clone { HERE; }
+}
+";
+        assert_eq!(
+            functions(source, Dialect::Rust),
+            named(&["main", "main", "inner", "type"])
+        );
+    }
+
+    #[test]
+    fn go_functions() {
+        let source = "\
+func main() {
+    go func() { HERE; }()
+}
+func (s *Server) Run(ctx context.Context) (int, error) { HERE; }
+";
+        assert_eq!(functions(source, Dialect::Go), named(&["main", "Run"]));
+    }
+}
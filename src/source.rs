@@ -0,0 +1,234 @@
+// Reading and writing source files without changing anything flop did not edit.
+// The UTF-8 byte order mark is kept aside, so that match offsets and edits only
+// deal with the text itself. Files in legacy encodings are decoded on request
+// and written back in the same encoding.
+//
+// All files edited in a run are written together: each one is replaced
+// atomically, and if one fails the files already written are restored.
+
+use anyhow::{bail, Context, Result};
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_8};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tempfile::Builder;
+
+const BOM: &str = "\u{feff}";
+
+// Like git and ripgrep, a NUL byte near the start means the file is binary
+const BINARY_CHECK_LEN: usize = 8000;
+
+// How files that are not valid UTF-8 are decoded
+#[derive(Debug, Clone, Copy)]
+pub enum Decoding {
+    Utf8Only,
+    Fixed(&'static Encoding),
+    Detect,
+}
+
+impl Decoding {
+    // Parse --encoding: "auto" or an encoding label such as "latin1" or "shift_jis"
+    pub fn from_label(label: Option<&str>) -> Result<Self> {
+        match label {
+            None => Ok(Decoding::Utf8Only),
+            Some(label) if label.eq_ignore_ascii_case("auto") => Ok(Decoding::Detect),
+            Some(label) => match Encoding::for_label(label.as_bytes()) {
+                Some(encoding) if encoding == UTF_8 => Ok(Decoding::Utf8Only),
+                Some(encoding) => Ok(Decoding::Fixed(encoding)),
+                None => bail!("Unknown encoding: {}", label),
+            },
+        }
+    }
+}
+
+pub enum Content {
+    Text(SourceFile),
+    Binary,
+    // Not text in the expected encoding, with the reason
+    Unreadable(String),
+}
+
+pub struct SourceFile {
+    pub text: String,
+    bom: bool,
+    encoding: &'static Encoding,
+}
+
+impl SourceFile {
+    pub fn read(path: &Path, decoding: Decoding) -> Result<Content> {
+        let bytes =
+            fs::read(path).with_context(|| format!("Failed to read file: {}", path.display()))?;
+
+        if bytes[..bytes.len().min(BINARY_CHECK_LEN)].contains(&0) {
+            return Ok(Content::Binary);
+        }
+
+        let (bom, body) = match bytes.strip_prefix(BOM.as_bytes()) {
+            Some(body) => (true, body),
+            None => (false, &bytes[..]),
+        };
+        if let Ok(text) = std::str::from_utf8(body) {
+            return Ok(Content::Text(Self {
+                text: text.to_string(),
+                bom,
+                encoding: UTF_8,
+            }));
+        }
+
+        // A UTF-8 byte order mark leaves no doubt about the encoding
+        if bom || matches!(decoding, Decoding::Utf8Only) {
+            return Ok(Content::Unreadable("not valid UTF-8".to_string()));
+        }
+        let encoding = match decoding {
+            Decoding::Utf8Only => unreachable!(),
+            Decoding::Fixed(encoding) => encoding,
+            Decoding::Detect => {
+                let mut detector = EncodingDetector::new();
+                detector.feed(&bytes, true);
+                detector.guess(None, true)
+            }
+        };
+
+        let Some(text) = encoding
+            .decode_without_bom_handling_and_without_replacement(&bytes)
+            .map(|text| text.into_owned())
+        else {
+            return Ok(Content::Unreadable(format!(
+                "not valid {}",
+                encoding.name()
+            )));
+        };
+        // Only edit files that can be written back byte for byte
+        let (encoded, _, _) = encoding.encode(&text);
+        if encoded[..] != bytes[..] {
+            return Ok(Content::Unreadable(format!(
+                "cannot be written back unchanged as {}",
+                encoding.name()
+            )));
+        }
+
+        Ok(Content::Text(Self {
+            text,
+            bom: false,
+            encoding,
+        }))
+    }
+
+    // Read a file that was scanned before, in the encoding found by the scan
+    pub fn read_as(path: &Path, encoding: &'static Encoding) -> Result<Self> {
+        let decoding = if encoding == UTF_8 {
+            Decoding::Utf8Only
+        } else {
+            Decoding::Fixed(encoding)
+        };
+        match Self::read(path, decoding)? {
+            Content::Text(source) => Ok(source),
+            Content::Binary => bail!("File is binary: {}", path.display()),
+            Content::Unreadable(reason) => bail!("File is {}: {}", reason, path.display()),
+        }
+    }
+
+    pub fn encoding(&self) -> &'static Encoding {
+        self.encoding
+    }
+
+    // `text` preceded by the byte order mark of the file, if it has one
+    pub fn with_bom(&self, text: &str) -> String {
+        if self.bom {
+            format!("{}{}", BOM, text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    // Bytes of `text` written in place of the original, in its encoding and
+    // keeping its byte order mark
+    pub fn encode(&self, path: &Path, text: &str) -> Result<Vec<u8>> {
+        let mut content = Vec::with_capacity(text.len() + BOM.len());
+        if self.bom {
+            content.extend_from_slice(BOM.as_bytes());
+        }
+        let (encoded, _, unmappable) = self.encoding.encode(text);
+        if unmappable {
+            bail!(
+                "Edited text cannot be written as {}: {}",
+                self.encoding.name(),
+                path.display()
+            );
+        }
+        content.extend_from_slice(&encoded);
+        Ok(content)
+    }
+}
+
+// Hash of a file's text, to tell whether it changed between scan and edit
+pub fn content_hash(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+// Replace the content of every file, or of none: if a write fails, the files
+// already written are restored to what they were before
+pub fn write_all(files: Vec<(PathBuf, Vec<u8>)>) -> Result<()> {
+    let mut written: Vec<(PathBuf, Vec<u8>)> = Vec::new();
+    for (path, content) in files {
+        let result = fs::read(&path)
+            .with_context(|| format!("Failed to read file: {}", path.display()))
+            .and_then(|original| {
+                write_atomic(&path, &content)?;
+                Ok(original)
+            });
+        match result {
+            Ok(original) => written.push((path, original)),
+            Err(err) => {
+                let mut failed = Vec::new();
+                for (path, original) in written.iter().rev() {
+                    if write_atomic(path, original).is_err() {
+                        failed.push(path.display().to_string());
+                    }
+                }
+                if !failed.is_empty() {
+                    return Err(err.context(format!(
+                        "Could not restore {} file(s): {}",
+                        failed.len(),
+                        failed.join(", ")
+                    )));
+                }
+                if !written.is_empty() {
+                    return Err(err.context(format!(
+                        "No file was changed: restored {} file(s) already written",
+                        written.len()
+                    )));
+                }
+                return Err(err);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Write to a temporary file next to `path` and rename it over `path`, so that
+// the file is never left half-written. Keeps the permissions of the original.
+fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    // Replace the target of a symlink rather than the link itself
+    let path = fs::canonicalize(path)
+        .with_context(|| format!("Failed to read file: {}", path.display()))?;
+    let permissions = fs::metadata(&path)
+        .with_context(|| format!("Failed to read file: {}", path.display()))?
+        .permissions();
+    let dir = path.parent().unwrap_or(Path::new("."));
+
+    let write = || -> std::io::Result<()> {
+        let mut temp = Builder::new().prefix(".flop").tempfile_in(dir)?;
+        temp.write_all(content)?;
+        temp.as_file().set_permissions(permissions)?;
+        temp.as_file().sync_all()?;
+        temp.persist(&path)?;
+        Ok(())
+    };
+    write().with_context(|| format!("Failed to write file: {}", path.display()))
+}
@@ -0,0 +1,191 @@
+// Syntax-aware detection backed by tree-sitter grammars.
+// Only statements that are a real call (or C++ stream expression) on their own
+// are reported, so output inside macro definitions, templates and larger
+// expressions is ignored.
+
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use std::path::Path;
+use tree_sitter::{Node, Parser};
+
+use crate::finder::{statement_match, Scanner};
+use crate::language::StatementEnd;
+use crate::lexer::Dialect;
+use crate::line_index::LineIndex;
+use crate::types::Match;
+
+pub(crate) fn find_statements(
+    file_path: &Path,
+    content: &str,
+    scanner: &Scanner,
+    keywords: &Regex,
+    require_keyword: bool,
+) -> Result<Vec<Match>> {
+    let mut parser = Parser::new();
+    parser.set_language(&scanner.language.grammar())?;
+    let tree = parser
+        .parse(content, None)
+        .ok_or_else(|| anyhow!("Failed to parse file: {}", file_path.display()))?;
+
+    let mut matches = Vec::new();
+    let lines = LineIndex::new(content);
+    let mut cursor = tree.walk();
+
+    // Depth-first walk over every node in the tree
+    'walk: loop {
+        let node = cursor.node();
+        if is_statement(node) {
+            if let Some(m) = statement_at(
+                file_path,
+                content,
+                &lines,
+                node,
+                scanner,
+                keywords,
+                require_keyword,
+            ) {
+                matches.push(m);
+            }
+        }
+
+        if cursor.goto_first_child() {
+            continue;
+        }
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                break 'walk;
+            }
+        }
+    }
+
+    Ok(matches)
+}
+
+fn is_statement(node: Node) -> bool {
+    match node.kind() {
+        "expression_statement" => true,
+        // Brace-delimited Rust macros need no `;` to be a statement
+        "macro_invocation" => {
+            node.parent()
+                .is_some_and(|p| p.kind() != "expression_statement")
+                && node
+                    .child_by_field_name("macro")
+                    .and_then(|m| m.next_sibling())
+                    .and_then(|bang| bang.next_sibling())
+                    .is_some_and(|args| args.child(0).is_some_and(|d| d.kind() == "{"))
+        }
+        _ => false,
+    }
+}
+
+// Check whether an expression statement is an output statement
+fn statement_at(
+    file_path: &Path,
+    content: &str,
+    lines: &LineIndex,
+    statement: Node,
+    scanner: &Scanner,
+    keywords: &Regex,
+    require_keyword: bool,
+) -> Option<Match> {
+    let expression = if statement.kind() == "macro_invocation" {
+        statement
+    } else {
+        statement.named_child(0)?
+    };
+    // Bounds of statements with syntax errors are guesses; leave them alone
+    if statement.has_error() {
+        return None;
+    }
+    let text = &content[statement.start_byte()..statement.end_byte()];
+
+    let caps = scanner.heads.captures(text)?;
+    let (pattern, head) = scanner.matched_pattern(&caps)?;
+    let kind_matches = match pattern.end {
+        StatementEnd::Call => matches!(
+            expression.kind(),
+            "call_expression" | "macro_invocation" | "method_invocation"
+        ),
+        StatementEnd::Terminator => expression.kind() == "binary_expression",
+    };
+    if head.start() != 0 || !kind_matches {
+        return None;
+    }
+
+    if require_keyword && pattern.requires_keyword && !keywords.is_match(text) {
+        return None;
+    }
+
+    let mut m = statement_match(
+        file_path,
+        content,
+        lines,
+        statement.start_byte(),
+        statement.end_byte(),
+        enclosing_function(content, statement, scanner.language.function_kinds()),
+        keywords,
+    );
+    m.parent_kind = parent_kind(statement);
+    m.control_body = is_control_body(statement, scanner.language.dialect());
+    Some(m)
+}
+
+// Whether the statement is the whole body of an unbraced `if`, loop or `else`, a
+// Java `case ->` rule, or follows a C or C++ label, so that removing it would give
+// its place to the next one
+fn is_control_body(statement: Node, dialect: Dialect) -> bool {
+    let mut prev = statement.prev_sibling();
+    while let Some(p) = prev.filter(|p| p.is_extra()) {
+        prev = p.prev_sibling();
+    }
+    prev.is_some_and(|p| match p.kind() {
+        ":" => matches!(dialect, Dialect::C | Dialect::Cpp),
+        "->" => true,
+        _ => false,
+    }) || statement.parent().is_some_and(|p| {
+        matches!(
+            p.kind(),
+            "if_statement"
+                | "else_clause"
+                | "for_statement"
+                | "for_range_loop"
+                | "enhanced_for_statement"
+                | "while_statement"
+                | "do_statement"
+                | "switch_statement"
+        )
+    })
+}
+
+// Kind of the statement or definition around `node`, like `if_statement` or
+// `function_definition`, looking through the braces of a block
+fn parent_kind(node: Node) -> Option<&'static str> {
+    let mut current = node.parent();
+    while let Some(n) = current {
+        if !matches!(n.kind(), "block" | "compound_statement") {
+            return Some(n.kind());
+        }
+        current = n.parent();
+    }
+    None
+}
+
+fn enclosing_function(content: &str, node: Node, function_kinds: &[&str]) -> Option<String> {
+    let mut current = node.parent();
+
+    while let Some(n) = current {
+        if function_kinds.contains(&n.kind()) {
+            // C/C++ names are nested inside (pointer/function) declarators
+            let mut name = n
+                .child_by_field_name("name")
+                .or_else(|| n.child_by_field_name("declarator"))?;
+            while let Some(inner) = name.child_by_field_name("declarator") {
+                name = inner;
+            }
+            return Some(content[name.start_byte()..name.end_byte()].to_string());
+        }
+        current = n.parent();
+    }
+
+    None
+}
@@ -1,10 +1,23 @@
+use encoding_rs::Encoding;
 use std::path::PathBuf;
 
 #[derive(Debug, Clone)]
 pub struct Match {
     pub file_path: PathBuf,
-    pub line_number: usize,             // Start line number
+    pub line_number: usize,                // Start line number
     pub end_line_number: usize, // End line number (same as line_number for single-line statements)
+    pub start_byte: usize,      // Byte offset of the statement in the file
+    pub end_byte: usize,        // Byte offset just past the statement
+    pub start_column: usize,    // 1-based byte column of the first character
+    pub end_column: usize,      // 1-based byte column just past the last character
     pub line_content: String,   // Single-line representation (for non-interactive display)
     pub multiline_content: Vec<String>, // Original lines for multiline display
+    pub function_name: Option<String>, // Enclosing function, when known
+    pub parent_kind: Option<&'static str>, // Syntax node around the statement (tree-sitter backend)
+    pub control_body: bool,     // Whole body of an unbraced `if`, loop or `else`, or after a label
+    pub keywords: Vec<String>,  // Distinct keyword matches in the statement, for highlighting
+    pub language: &'static str, // Language name, e.g. "c" or "rust"
+    pub commented: bool,        // Whether the statement is commented out
+    pub encoding: &'static Encoding, // Encoding the file was read in
+    pub file_hash: u64,         // Hash of the file text when it was scanned
 }
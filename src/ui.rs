@@ -9,18 +9,55 @@ use ratatui::{
     style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{
-        Block, Borders, HighlightSpacing, Row, Scrollbar, ScrollbarOrientation, ScrollbarState,
-        Table, TableState,
+        Block, Borders, Cell, HighlightSpacing, Row, Scrollbar, ScrollbarOrientation,
+        ScrollbarState, Table, TableState,
     },
     Frame, Terminal,
 };
 use regex::Regex;
 use std::collections::HashMap;
-use std::io;
+use std::env;
+use std::io::{self, IsTerminal};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 
+use crate::cli::ColorChoice;
 use crate::types::Match;
 
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+// Decide once whether text output is colored
+pub fn init_color(choice: ColorChoice) {
+    let enabled = match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        // https://no-color.org
+        ColorChoice::Auto => {
+            env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()) && io::stdout().is_terminal()
+        }
+    };
+    COLOR.store(enabled, Ordering::Relaxed);
+}
+
+pub fn color_enabled() -> bool {
+    COLOR.load(Ordering::Relaxed)
+}
+
+// `text` wrapped in an ANSI style such as "32" (green), when colors are on
+pub fn paint(style: &str, text: &str) -> String {
+    if color_enabled() {
+        format!("\x1b[{}m{}\x1b[0m", style, text)
+    } else {
+        text.to_string()
+    }
+}
+
+// Whether the interactive selection can run: it needs a terminal to draw on
+// and to read keys from
+pub fn can_select_interactively() -> bool {
+    io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
 pub fn display_matches(matches: &[Match]) {
     // Display all matches grouped by file
     println!("\nFound {} debug statement(s):\n", matches.len());
@@ -38,21 +75,21 @@ pub fn display_matches(matches: &[Match]) {
 
     for (file_path, file_matches) in sorted_files {
         // Display filename in color (magenta like ripgrep)
-        println!("\x1b[35m{}\x1b[0m", file_path.display());
+        println!("{}", paint("35", &file_path.display().to_string()));
 
         // Sort matches by line number
         let mut sorted_matches = file_matches.clone();
         sorted_matches.sort_by_key(|m| m.line_number);
 
         for m in sorted_matches {
-            // Line number in green, followed by colon and content with highlighted debug keyword
-            let highlighted = highlight_debug_keyword(&m.line_content);
+            // Line number in green, followed by colon and content with highlighted keywords
+            let highlighted = highlight_keywords(&m.line_content, &m.keywords);
             let line_display = if m.line_number == m.end_line_number {
                 format!("{}", m.line_number)
             } else {
                 format!("{}-{}", m.line_number, m.end_line_number)
             };
-            println!("\x1b[32m{}\x1b[0m:{}", line_display, highlighted.trim());
+            println!("{}:{}", paint("32", &line_display), highlighted.trim());
         }
 
         println!(); // Empty line between files
@@ -70,10 +107,45 @@ pub fn select_statements_interactive(matches: &[Match]) -> Result<Vec<Match>> {
     Ok(selected)
 }
 
-fn highlight_debug_keyword(line: &str) -> String {
-    // Highlight "debug" or "DEBUG" keywords in red
-    let re = Regex::new(r"(debug|DEBUG)").unwrap();
-    re.replace_all(line, "\x1b[1;31m$1\x1b[0m").to_string()
+fn highlight_keywords(line: &str, keywords: &[String]) -> String {
+    // Highlight the keywords found in the statement in red
+    match keyword_regex(keywords) {
+        Some(re) if color_enabled() => re.replace_all(line, "\x1b[1;31m$0\x1b[0m").to_string(),
+        _ => line.to_string(),
+    }
+}
+
+// Build a TUI line with the keywords found in the statement highlighted
+fn highlighted_line(line: &str, keywords: &[String]) -> Line<'static> {
+    let Some(re) = keyword_regex(keywords) else {
+        return Line::from(line.to_string());
+    };
+
+    let keyword_style = Style::default().fg(Color::Red).add_modifier(Modifier::BOLD);
+    let mut spans = Vec::new();
+    let mut last = 0;
+    for k in re.find_iter(line) {
+        spans.push(Span::raw(line[last..k.start()].to_string()));
+        spans.push(Span::styled(k.as_str().to_string(), keyword_style));
+        last = k.end();
+    }
+    spans.push(Span::raw(line[last..].to_string()));
+    Line::from(spans)
+}
+
+// Match the exact keyword text, longest first so overlapping keywords highlight fully
+fn keyword_regex(keywords: &[String]) -> Option<Regex> {
+    if keywords.is_empty() {
+        return None;
+    }
+    let mut keywords: Vec<&String> = keywords.iter().collect();
+    keywords.sort_by_key(|k| std::cmp::Reverse(k.len()));
+    let pattern = keywords
+        .iter()
+        .map(|k| regex::escape(k))
+        .collect::<Vec<_>>()
+        .join("|");
+    Regex::new(&pattern).ok()
 }
 
 struct App {
@@ -373,27 +445,27 @@ impl App {
             if m.multiline_content.len() > 1 {
                 // First line - selectable
                 rows.push(Row::new(vec![
-                    checkbox.to_string(),
-                    line_display.clone(),
-                    m.multiline_content[0].trim().to_string(),
+                    Cell::from(checkbox),
+                    Cell::from(line_display.clone()),
+                    Cell::from(highlighted_line(m.multiline_content[0].trim(), &m.keywords)),
                 ]));
                 row_to_match.push(Some(*original_idx));
 
                 // Continuation lines - not selectable
                 for line in &m.multiline_content[1..] {
                     rows.push(Row::new(vec![
-                        "    ".to_string(), // No checkbox
-                        "...".to_string(),  // Continuation marker
-                        line.trim().to_string(),
+                        Cell::from("    "), // No checkbox
+                        Cell::from("..."),  // Continuation marker
+                        Cell::from(highlighted_line(line.trim(), &m.keywords)),
                     ]));
                     row_to_match.push(None); // Not selectable
                 }
             } else {
                 // Single line
                 rows.push(Row::new(vec![
-                    checkbox.to_string(),
-                    line_display,
-                    m.line_content.trim().to_string(),
+                    Cell::from(checkbox),
+                    Cell::from(line_display),
+                    Cell::from(highlighted_line(m.line_content.trim(), &m.keywords)),
                 ]));
                 row_to_match.push(Some(*original_idx));
             }
@@ -0,0 +1,52 @@
+// Nested `.flop.toml` files override the settings of their parents for their
+// subtree, lists included.
+
+use std::fs;
+
+use flop_cli::config::{Config, CONFIG_FILE};
+
+#[test]
+fn nested_config_replaces_lists() {
+    let dir = tempfile::tempdir().unwrap();
+    let sub = dir.path().join("sub");
+    fs::create_dir(&sub).unwrap();
+    fs::write(
+        dir.path().join(CONFIG_FILE),
+        "\
+debug = true
+keywords = [\"debug\", \"XXX\"]
+
+[languages.c]
+extensions = [\"pc\"]
+functions = [\"LOG_INFO\"]
+debug_functions = [\"LOG_DBG\"]
+",
+    )
+    .unwrap();
+    fs::write(
+        sub.join(CONFIG_FILE),
+        "\
+keywords = [\"TRACE\"]
+
+[languages.c]
+functions = [\"TRACE_INFO\"]
+
+[languages.rust]
+debug_functions = [\"trace_here!\"]
+",
+    )
+    .unwrap();
+
+    let config = Config::for_path(&sub).unwrap();
+    assert_eq!(config.debug, Some(true));
+    assert_eq!(config.keywords, ["TRACE"]);
+    let c = &config.languages["c"];
+    assert_eq!(c.extensions, ["pc"]);
+    assert_eq!(c.functions, ["TRACE_INFO"]);
+    assert_eq!(c.debug_functions, ["LOG_DBG"]);
+    assert_eq!(config.languages["rust"].debug_functions, ["trace_here!"]);
+
+    let config = Config::for_path(dir.path()).unwrap();
+    assert_eq!(config.keywords, ["debug", "XXX"]);
+    assert_eq!(config.languages["c"].functions, ["LOG_INFO"]);
+}
@@ -0,0 +1,50 @@
+// Files that cannot be decoded are skipped with the reason they were rejected,
+// or stop the run with --strict-encoding.
+
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn skipped_files_are_reported_with_their_reason() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("l.c"),
+        b"int main() {\n    printf(\"caf\xe9\");\n}\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("u.c"),
+        "int main() {\n    printf(\"ok\");\n}\n",
+    )
+    .unwrap();
+
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_flop"))
+            .arg("list")
+            .arg(dir.path())
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["--skip-unreadable"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(
+            "Skipped 1 file(s) that cannot be decoded (1 not valid UTF-8); use --encoding"
+        ),
+        "{}",
+        stderr
+    );
+
+    let output = run(&["--encoding", "utf-16le"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Skipped 1 file(s) that cannot be decoded (1 not valid UTF-16LE)\n"),
+        "{}",
+        stderr
+    );
+
+    assert!(!run(&["--strict-encoding"]).status.success());
+}
@@ -0,0 +1,73 @@
+// --changed, --staged and --since must find the statements added on disk, even
+// when the lines reported by git come from the index or another commit.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use flop_cli::cli::ScanOptions;
+use flop_cli::finder::find_debug_printfs;
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["-c", "user.name=flop", "-c", "user.email=flop@example.com"])
+        .args(args)
+        .status()
+        .unwrap();
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+fn repository(content: &str) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    git(dir.path(), &["init", "-q"]);
+    fs::write(dir.path().join("m.c"), content).unwrap();
+    git(dir.path(), &["add", "m.c"]);
+    git(dir.path(), &["commit", "-qm", "initial"]);
+    dir
+}
+
+fn staged_lines(dir: &Path) -> Vec<usize> {
+    let mut scan = ScanOptions::default();
+    scan.diff.staged = true;
+    find_debug_printfs(&dir.join("m.c"), false, true, &scan)
+        .unwrap()
+        .iter()
+        .map(|m| m.line_number)
+        .collect()
+}
+
+#[test]
+fn staged_statement_is_found_below_unstaged_lines() {
+    let dir = repository("int main() {\n    return 0;\n}\n");
+    let path = dir.path().join("m.c");
+    fs::write(
+        &path,
+        "int main() {\n    printf(\"debug staged\");\n    return 0;\n}\n",
+    )
+    .unwrap();
+    git(dir.path(), &["add", "m.c"]);
+    fs::write(
+        &path,
+        "int main() {\n    int a;\n    int b;\n    int c;\n    printf(\"debug staged\");\n    return 0;\n}\n",
+    )
+    .unwrap();
+
+    assert_eq!(staged_lines(dir.path()), vec![5]);
+}
+
+#[test]
+fn unstaged_statement_is_not_staged() {
+    let dir = repository("int main() {\n    return 0;\n}\n");
+    let path = dir.path().join("m.c");
+    fs::write(&path, "int main() {\n    int a;\n    return 0;\n}\n").unwrap();
+    git(dir.path(), &["add", "m.c"]);
+    fs::write(
+        &path,
+        "int main() {\n    printf(\"debug unstaged\");\n    int a;\n    return 0;\n}\n",
+    )
+    .unwrap();
+
+    assert!(staged_lines(dir.path()).is_empty());
+}
@@ -0,0 +1,169 @@
+// `flop undo` must revert exactly what `on`, `off` and `delete` wrote, and refuse
+// to touch files whose lines changed since.
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+use flop_cli::cli::ScanOptions;
+use flop_cli::editor::{apply_changes, delete_changes};
+use flop_cli::finder::find_debug_printfs;
+use flop_cli::journal;
+
+const SOURCE: &str = "int main() {\n    int x = 1;\n    printf(\"%d\\n\", x);\n    return 0;\n}\n";
+
+fn flop(cwd: &Path, args: &[&str]) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_flop"))
+        .current_dir(cwd)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "flop {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+// The journal belongs to where flop runs, whatever the paths it edits
+#[test]
+fn undo_finds_operations_on_files_outside_the_repository() {
+    let repo = tempfile::tempdir().unwrap();
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(repo.path())
+        .args(["init", "-q"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let sub = repo.path().join("sub");
+    fs::create_dir(&sub).unwrap();
+
+    let other = tempfile::tempdir().unwrap();
+    let path = other.path().join("x.c");
+    fs::write(&path, SOURCE).unwrap();
+
+    flop(&sub, &["on", path.to_str().unwrap(), "-y"]);
+    assert_ne!(fs::read_to_string(&path).unwrap(), SOURCE);
+    flop(&sub, &["undo"]);
+    assert_eq!(fs::read_to_string(&path).unwrap(), SOURCE);
+
+    assert!(repo.path().join(".flop").is_dir());
+    assert!(!sub.join(".flop").exists());
+    assert!(!other.path().join(".flop").exists());
+}
+
+// Disable every statement of `path` and record it in the journal of `dir`
+fn disable(dir: &Path, path: &Path) -> u64 {
+    let matches = find_debug_printfs(path, false, true, &ScanOptions::default()).unwrap();
+    let (_, changes) = apply_changes(&matches, false).unwrap();
+    journal::record(dir, "on", changes).unwrap()
+}
+
+#[test]
+fn undo_restores_the_exact_bytes() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("m.c");
+    let content = SOURCE.replace('\n', "\r\n");
+    fs::write(&path, &content).unwrap();
+
+    disable(dir.path(), &path);
+    assert_ne!(fs::read_to_string(&path).unwrap(), content);
+    journal::undo(dir.path(), None).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), content);
+    assert!(journal::list(dir.path()).unwrap().is_empty());
+}
+
+#[test]
+fn undo_restores_a_deleted_last_line_without_newline() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("m.c");
+    let content = "int x;\nprintf(\"%d\", x);";
+    fs::write(&path, content).unwrap();
+
+    let matches = find_debug_printfs(&path, false, true, &ScanOptions::default()).unwrap();
+    let (_, changes) = delete_changes(&matches).unwrap();
+    journal::record(dir.path(), "delete", changes).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "int x;");
+
+    journal::undo(dir.path(), None).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), content);
+}
+
+// Statements far apart are separate hunks, each found again when lines are
+// added elsewhere in the file
+#[test]
+fn undo_finds_hunks_after_lines_move() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("m.c");
+    let filler = "    x++;\n".repeat(10);
+    let content = format!(
+        "void f(int x) {{\n    puts(\"a\");\n    puts(\"b\");\n{}    puts(\"c\");\n}}\n",
+        filler
+    );
+    fs::write(&path, &content).unwrap();
+
+    let matches = find_debug_printfs(&path, false, true, &ScanOptions::default()).unwrap();
+    let (_, changes) = apply_changes(&matches, false).unwrap();
+    assert_eq!(changes[0].hunks.len(), 2);
+    journal::record(dir.path(), "on", changes).unwrap();
+
+    let disabled = fs::read_to_string(&path).unwrap();
+    fs::write(&path, format!("#include <stdio.h>\n\n{}", disabled)).unwrap();
+    journal::undo(dir.path(), None).unwrap();
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        format!("#include <stdio.h>\n\n{}", content)
+    );
+}
+
+// A changed line in one file leaves every file of the operation alone
+#[test]
+fn undo_refuses_when_a_line_changed() {
+    let dir = tempfile::tempdir().unwrap();
+    let (a, b) = (dir.path().join("a.c"), dir.path().join("b.c"));
+    fs::write(&a, SOURCE).unwrap();
+    fs::write(&b, SOURCE).unwrap();
+    let id = disable(dir.path(), dir.path());
+
+    let disabled = fs::read_to_string(&a).unwrap();
+    let edited = fs::read_to_string(&b).unwrap().replace(
+        "//flop: printf(\"%d\\n\", x);",
+        "//flop: printf(\"%d\", x);",
+    );
+    fs::write(&b, &edited).unwrap();
+
+    let err = journal::undo(dir.path(), None).unwrap_err().to_string();
+    assert!(err.contains("changed since"), "{}", err);
+    assert_eq!(fs::read_to_string(&a).unwrap(), disabled);
+    assert_eq!(fs::read_to_string(&b).unwrap(), edited);
+    assert_eq!(journal::list(dir.path()).unwrap()[0].id, id);
+}
+
+#[test]
+fn ids_are_never_reused() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("m.c");
+    fs::write(&path, SOURCE).unwrap();
+
+    assert_eq!(disable(dir.path(), &path), 1);
+    journal::undo(dir.path(), Some(1)).unwrap();
+    assert_eq!(disable(dir.path(), &path), 2);
+    assert!(journal::undo(dir.path(), Some(1)).is_err());
+}
+
+#[test]
+fn old_operations_are_dropped() {
+    let dir = tempfile::tempdir().unwrap();
+    for _ in 0..55 {
+        journal::record(dir.path(), "on", Vec::new()).unwrap();
+    }
+    let ids: Vec<u64> = journal::list(dir.path())
+        .unwrap()
+        .iter()
+        .map(|op| op.id)
+        .collect();
+    assert_eq!(ids, (6..=55).collect::<Vec<_>>());
+}
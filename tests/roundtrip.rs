@@ -0,0 +1,290 @@
+// `flop off` must be the exact inverse of `flop on`: disabling any set of
+// statements in the sample files and enabling them again gives back the
+// original bytes, whatever the indentation, spacing and line endings.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use flop_cli::cli::ScanOptions;
+use flop_cli::editor::{apply_changes, delete_changes};
+use flop_cli::finder::find_debug_printfs;
+use flop_cli::language::{self, DISABLED_TAG};
+use proptest::prelude::*;
+
+// Sample files, except the expected outputs of `flop on` used by CI
+fn samples() -> Vec<PathBuf> {
+    fn walk(dir: &Path, files: &mut Vec<PathBuf>) {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                walk(&path, files);
+            } else if language::for_path(&path).is_some()
+                && !fs::read_to_string(&path).unwrap().contains(DISABLED_TAG)
+            {
+                files.push(path);
+            }
+        }
+    }
+
+    let mut files = Vec::new();
+    walk(
+        &Path::new(env!("CARGO_MANIFEST_DIR")).join("sample"),
+        &mut files,
+    );
+    files.sort();
+    files
+}
+
+// Run `on` over the selected statements of `content`, then `off` over everything
+// flop disabled. Returns the content after each step.
+fn on_off(file_name: &str, content: &str, select: &[bool]) -> (String, String) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(file_name);
+    fs::write(&path, content).unwrap();
+    let scan = ScanOptions::default();
+
+    let matches = find_debug_printfs(&path, false, true, &scan).unwrap();
+    let selected: Vec<_> = matches
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| select.is_empty() || select[i % select.len()])
+        .map(|(_, m)| m)
+        .collect();
+    apply_changes(&selected, false).unwrap();
+    let disabled = fs::read_to_string(&path).unwrap();
+
+    let matches = find_debug_printfs(&path, true, true, &scan).unwrap();
+    apply_changes(&matches, true).unwrap();
+    let enabled = fs::read_to_string(&path).unwrap();
+
+    (disabled, enabled)
+}
+
+// Rewrite a file with another line terminator, final newline and byte order mark
+fn with_line_endings(content: &str, crlf: bool, final_newline: bool, bom: bool) -> String {
+    let terminator = if crlf { "\r\n" } else { "\n" };
+    let mut result = content.lines().collect::<Vec<_>>().join(terminator);
+    if final_newline {
+        result.push_str(terminator);
+    }
+    if bom {
+        result.insert(0, '\u{feff}');
+    }
+    result
+}
+
+#[test]
+fn every_sample_round_trips() {
+    let samples = samples();
+    assert!(!samples.is_empty());
+
+    for path in samples {
+        let content = fs::read_to_string(&path).unwrap();
+        let name = path.file_name().unwrap().to_str().unwrap();
+        let (disabled, enabled) = on_off(name, &content, &[]);
+        assert_ne!(disabled, content, "nothing disabled in {}", path.display());
+        assert_eq!(enabled, content, "{} did not round-trip", path.display());
+    }
+}
+
+// `flop on` comments out a line shared by two statements as a whole; both are
+// then disabled, and can be deleted one at a time
+#[test]
+fn statements_sharing_a_line() {
+    let content = "int main() {\n    printf(\"a\"); printf(\"b\");\n    return 0;\n}\n";
+    let (disabled, enabled) = on_off("m.c", content, &[]);
+    assert!(disabled.contains("    //flop: printf(\"a\"); printf(\"b\");\n"));
+    assert_eq!(enabled, content);
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("m.c");
+    let scan = ScanOptions::default();
+    for (delete, left) in [(0, "printf(\"b\");"), (1, "printf(\"a\");"), (2, "")] {
+        fs::write(&path, &disabled).unwrap();
+        let matches = find_debug_printfs(&path, true, true, &scan).unwrap();
+        assert_eq!(matches.len(), 2);
+        let selected = match delete {
+            2 => matches,
+            i => vec![matches[i].clone()],
+        };
+        delete_changes(&selected).unwrap();
+        let expected = match left {
+            "" => "int main() {\n    return 0;\n}\n".to_string(),
+            left => format!("int main() {{\n    //flop: {}\n    return 0;\n}}\n", left),
+        };
+        assert_eq!(fs::read_to_string(&path).unwrap(), expected);
+    }
+}
+
+// A statement sharing its line with other code is wrapped in `/* */`, which it
+// cannot contain; it is skipped and not counted
+#[test]
+fn statement_containing_comment_close_is_skipped() {
+    let content = "int main() {\n    int x = 1; printf(\"*/\"); printf(\"a\");\n}\n";
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("m.c");
+    fs::write(&path, content).unwrap();
+
+    let matches = find_debug_printfs(&path, false, true, &ScanOptions::default()).unwrap();
+    assert_eq!(matches.len(), 2);
+    let (statements, _) = apply_changes(&matches, false).unwrap();
+    assert_eq!(statements, 1);
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "int main() {\n    int x = 1; printf(\"*/\"); /*flop: printf(\"a\"); */\n}\n"
+    );
+}
+
+// `flop delete` of every statement found in `content`, active or disabled
+fn deleted(file_name: &str, content: &str) -> String {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(file_name);
+    fs::write(&path, content).unwrap();
+    let scan = ScanOptions::default();
+    let mut matches = find_debug_printfs(&path, false, true, &scan).unwrap();
+    matches.extend(find_debug_printfs(&path, true, true, &scan).unwrap());
+    delete_changes(&matches).unwrap();
+    fs::read_to_string(&path).unwrap()
+}
+
+// The body of an unbraced `if`, loop, `else` or label is disabled or deleted as
+// an empty block, so that the next statement does not become the body
+#[test]
+fn unbraced_bodies_keep_a_statement() {
+    let cases = [
+        (
+            "m.c",
+            "void f(int x) {\n    if (x) printf(\"b\");\n    launch();\n}\n",
+            "void f(int x) {\n    if (x) /*flop: printf(\"b\"); */{}\n    launch();\n}\n",
+            "void f(int x) {\n    if (x) {}\n    launch();\n}\n",
+        ),
+        (
+            "m.c",
+            "void f(int n) {\n    for (int i = 0; i < n; i++) printf(\"%d\", i);\n    launch();\n}\n",
+            "void f(int n) {\n    for (int i = 0; i < n; i++) /*flop: printf(\"%d\", i); */{}\n    launch();\n}\n",
+            "void f(int n) {\n    for (int i = 0; i < n; i++) {}\n    launch();\n}\n",
+        ),
+        (
+            "m.c",
+            "void f(int x) {\n    if (x)\n        printf(\"b\");\n    else\n        puts(\"c\");\n    launch();\n}\n",
+            "void f(int x) {\n    if (x)\n        /*flop: printf(\"b\"); */{}\n    else\n        /*flop: puts(\"c\"); */{}\n    launch();\n}\n",
+            "void f(int x) {\n    if (x)\n        {}\n    else\n        {}\n    launch();\n}\n",
+        ),
+        (
+            "m.cpp",
+            "void f(int x) {\n    switch (x) {\n    case 1: std::printf(\"a\");\n    }\n}\n",
+            "void f(int x) {\n    switch (x) {\n    case 1: /*flop: std::printf(\"a\"); */{}\n    }\n}\n",
+            "void f(int x) {\n    switch (x) {\n    case 1: {}\n    }\n}\n",
+        ),
+        (
+            "M.java",
+            "class M {\n    void f(int x) {\n        if (x > 0) go(); else System.out.println(\"e\");\n        launch();\n    }\n}\n",
+            "class M {\n    void f(int x) {\n        if (x > 0) go(); else /*flop: System.out.println(\"e\"); */{}\n        launch();\n    }\n}\n",
+            "class M {\n    void f(int x) {\n        if (x > 0) go(); else {}\n        launch();\n    }\n}\n",
+        ),
+    ];
+
+    for (name, content, disabled, after_delete) in cases {
+        let (on, off) = on_off(name, content, &[]);
+        assert_eq!(on, disabled);
+        assert_eq!(off, content);
+        assert_eq!(deleted(name, content), after_delete);
+        assert_eq!(deleted(name, disabled), after_delete);
+    }
+}
+
+// Output whose result is used is part of a larger statement, and is left alone
+#[test]
+fn output_used_as_value_is_not_a_statement() {
+    let cases = [
+        (
+            "m.c",
+            "int f(void) {\n    int n = printf(\"a\");\n    return printf(\"c\");\n}\n",
+        ),
+        ("m.rs", "fn f(x: i32) {\n    let y = dbg!(x);\n}\n"),
+        (
+            "m.go",
+            "package main\n\nfunc f() {\n\tdefer fmt.Println(\"a\")\n\tgo fmt.Println(\"b\")\n\tn, _ := fmt.Println(\"c\")\n}\n",
+        ),
+    ];
+
+    for (name, content) in cases {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(name);
+        fs::write(&path, content).unwrap();
+        let matches = find_debug_printfs(&path, false, true, &ScanOptions::default()).unwrap();
+        assert!(
+            matches.is_empty(),
+            "{} found in {}",
+            matches[0].line_content,
+            name
+        );
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    // Reindent every line with arbitrary blanks, add trailing blanks and only
+    // disable some of the statements
+    #[test]
+    fn reformatted_samples_round_trip(
+        sample in any::<prop::sample::Index>(),
+        indents in prop::collection::vec("[ \t]{0,8}", 1..16),
+        trailing in prop::collection::vec("[ \t]{0,3}", 1..16),
+        select in prop::collection::vec(any::<bool>(), 1..16),
+        crlf in any::<bool>(),
+        final_newline in any::<bool>(),
+        bom in any::<bool>(),
+    ) {
+        let samples = samples();
+        let path = sample.get(&samples);
+        let original = fs::read_to_string(path).unwrap();
+
+        let content: String = original
+            .lines()
+            .enumerate()
+            .map(|(i, line)| {
+                format!(
+                    "{}{}{}\n",
+                    indents[i % indents.len()],
+                    line.trim_start(),
+                    trailing[i % trailing.len()]
+                )
+            })
+            .collect();
+        let content = with_line_endings(&content, crlf, final_newline, bom);
+
+        let name = path.file_name().unwrap().to_str().unwrap();
+        let (_, enabled) = on_off(name, &content, &select);
+        prop_assert_eq!(enabled, content);
+    }
+
+    // Deleting statements keeps the line terminators, final newline and byte
+    // order mark of the file
+    #[test]
+    fn delete_keeps_line_endings(
+        sample in any::<prop::sample::Index>(),
+        crlf in any::<bool>(),
+        final_newline in any::<bool>(),
+        bom in any::<bool>(),
+    ) {
+        let samples = samples();
+        let path = sample.get(&samples);
+        let content = with_line_endings(&fs::read_to_string(path).unwrap(), crlf, final_newline, bom);
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join(path.file_name().unwrap());
+        fs::write(&file, &content).unwrap();
+        let matches = find_debug_printfs(&file, false, true, &ScanOptions::default()).unwrap();
+        delete_changes(&matches).unwrap();
+        let deleted = fs::read_to_string(&file).unwrap();
+
+        prop_assert_ne!(&deleted, &content);
+        prop_assert_eq!(deleted.starts_with('\u{feff}'), bom);
+        prop_assert_eq!(deleted.ends_with('\n'), final_newline);
+        let bare_newlines = deleted.matches('\n').count() - deleted.matches("\r\n").count();
+        prop_assert_eq!(bare_newlines == 0, crlf || !deleted.contains('\n'));
+        prop_assert_eq!(deleted.contains('\r'), crlf && deleted.contains('\n'));
+    }
+}
@@ -0,0 +1,41 @@
+// Files edited between the scan and the edit, as when an editor autosaves while
+// the selection UI is open, must never get the wrong line commented out.
+
+use std::fs;
+
+use flop_cli::cli::ScanOptions;
+use flop_cli::editor::apply_changes;
+use flop_cli::finder::find_debug_printfs;
+
+const SOURCE: &str = "int main() {\n    int x = 1;\n    printf(\"%d\\n\", x);\n    return 0;\n}\n";
+
+#[test]
+fn statements_are_found_again_after_lines_are_added() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("main.c");
+    fs::write(&path, SOURCE).unwrap();
+    let matches = find_debug_printfs(&path, false, true, &ScanOptions::default()).unwrap();
+
+    fs::write(&path, format!("#include <stdio.h>\n\n{}", SOURCE)).unwrap();
+    let (statements, _) = apply_changes(&matches, false).unwrap();
+    assert_eq!(statements, 1);
+
+    let content = fs::read_to_string(&path).unwrap();
+    assert!(content.contains("    //flop: printf(\"%d\\n\", x);\n"));
+    assert!(content.contains("    int x = 1;\n    //flop:"));
+}
+
+#[test]
+fn file_is_left_alone_when_a_statement_is_gone() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("main.c");
+    fs::write(&path, SOURCE).unwrap();
+    let matches = find_debug_printfs(&path, false, true, &ScanOptions::default()).unwrap();
+
+    let edited = SOURCE.replace("printf(\"%d\\n\", x);", "puts(\"x\");");
+    fs::write(&path, &edited).unwrap();
+    let (statements, _) = apply_changes(&matches, false).unwrap();
+    assert_eq!(statements, 0);
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), edited);
+}
@@ -0,0 +1,43 @@
+// With the tree-sitter backend, statements come from syntax nodes and carry the
+// enclosing function and the kind of node around them.
+#![cfg(feature = "tree-sitter")]
+
+use std::fs;
+
+use flop_cli::cli::ScanOptions;
+use flop_cli::finder::find_debug_printfs;
+
+#[test]
+fn statements_know_their_function_and_parent() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("m.c");
+    fs::write(
+        &path,
+        "\
+#define TRACE(x) printf(\"%d\", x);
+int main(int argc) {
+    printf(\"a\");
+    if (argc) {
+        printf(\"b\");
+    }
+    for (;;) printf(\"c\");
+    return printf(\"d\");
+}
+",
+    )
+    .unwrap();
+
+    let matches = find_debug_printfs(&path, false, true, &ScanOptions::default()).unwrap();
+    let found: Vec<_> = matches
+        .iter()
+        .map(|m| (m.line_number, m.function_name.as_deref(), m.parent_kind))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (3, Some("main"), Some("function_definition")),
+            (5, Some("main"), Some("if_statement")),
+            (7, Some("main"), Some("for_statement")),
+        ]
+    );
+}
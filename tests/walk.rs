@@ -0,0 +1,55 @@
+// Filters given on the command line narrow down the files searched, but never
+// bring back files that are ignored.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use flop_cli::cli::ScanOptions;
+use flop_cli::finder::find_debug_printfs;
+
+const SOURCE: &str = "int main() {\n    printf(\"x\");\n}\n";
+
+fn tree(files: &[&str]) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    for file in files {
+        let path = dir.path().join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, SOURCE).unwrap();
+    }
+    dir
+}
+
+fn found(dir: &Path, scan: &ScanOptions) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = find_debug_printfs(dir, false, true, scan)
+        .unwrap()
+        .into_iter()
+        .map(|m| m.file_path.strip_prefix(dir).unwrap().to_path_buf())
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn include_does_not_override_gitignore() {
+    let dir = tree(&["a.c", "gen.c", "src/b.c", "src/b.h"]);
+    fs::write(dir.path().join(".gitignore"), "gen.c\n").unwrap();
+
+    let mut scan = ScanOptions::default();
+    scan.walk.include = vec!["*.c".to_string()];
+    assert_eq!(
+        found(dir.path(), &scan),
+        vec![PathBuf::from("a.c"), PathBuf::from("src/b.c")]
+    );
+}
+
+#[test]
+fn hidden_does_not_search_git_directory() {
+    let dir = tree(&["a.c", ".git/foo/x.c", ".hidden/h.c"]);
+
+    let mut scan = ScanOptions::default();
+    scan.walk.hidden = true;
+    assert_eq!(
+        found(dir.path(), &scan),
+        vec![PathBuf::from(".hidden/h.c"), PathBuf::from("a.c")]
+    );
+}
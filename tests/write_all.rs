@@ -0,0 +1,35 @@
+// A run writes every file it edits or none of them: when one write fails, the
+// files already written get their content back.
+
+use std::fs;
+
+use flop_cli::source::write_all;
+
+#[test]
+fn failed_write_restores_the_files_already_written() {
+    let dir = tempfile::tempdir().unwrap();
+    let first = dir.path().join("a.c");
+    fs::write(&first, "printf(\"a\");\n").unwrap();
+    // A path under a regular file can be neither read nor written
+    let not_a_dir = dir.path().join("b.c");
+    fs::write(&not_a_dir, "").unwrap();
+    let second = not_a_dir.join("c.c");
+
+    let err = write_all(vec![
+        (first.clone(), b"//flop: printf(\"a\");\n".to_vec()),
+        (second, b"//flop: printf(\"c\");\n".to_vec()),
+    ])
+    .unwrap_err();
+
+    assert_eq!(fs::read_to_string(&first).unwrap(), "printf(\"a\");\n");
+    let message = format!("{:#}", err);
+    assert!(message.contains("No file was changed"), "{}", message);
+
+    // No temporary file is left behind
+    let mut names: Vec<_> = fs::read_dir(dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["a.c", "b.c"]);
+}